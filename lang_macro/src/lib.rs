@@ -13,32 +13,87 @@
 // limitations under the License.
 
 use proc_macro::TokenStream;
-use quote::quote;
+use quote::{
+    quote,
+    ToTokens,
+};
 
 /// The macro is used to do some initial set-up for a waterfall test and handle
 /// the shutdown at the end of a test.
 ///
+/// Each test is executed against its own `canvas-node`, which is spawned before
+/// and killed after the test (see `CanvasNode::start`). The chain state the node
+/// starts with is chosen by the `WATERFALL_CHAIN` environment variable, unless
+/// the test specifies one explicitly via `chain = "fresh"` or `chain = "snapshot"`.
+///
+/// The backend which the test is executed against is bound to the name of the
+/// first function argument. It is chosen by the `WATERFALL_BACKEND` environment
+/// variable, unless the test specifies one explicitly via `backend = "…"`.
+///
+/// The test fails if it doesn't finish within `WATERFALL_TIMEOUT_SECS_PER_TEST`
+/// seconds, unless the test specifies a timeout explicitly via e.g.
+/// `timeout = "120s"` (supported units are `ms`, `s`, `m` and `h`). The timeout
+/// covers the whole test, including the contract builds, which run on blocking
+/// threads (see `cargo_contract::build`). The backend is shut down in any case,
+/// also if the test timed out or panicked.
+///
+/// If the test fails, artifacts which help to diagnose the failure (e.g. a
+/// screenshot and the node log) are saved to `artifacts/<test>` before the
+/// shutdown (see `artifacts::save`).
+///
 /// # Usage
 ///
 /// ```no_compile
 /// #[waterfall_test]
-/// async fn works(mut canvas_ui: CanvasUi) -> Result<()> {
-///     let _contract_addr = canvas_ui.upload(contract_file).await?;
+/// async fn works(mut canvas_ui: Box<dyn ContractsBackend>) -> Result<()> {
+///     let _contract_addr = canvas_ui.execute_upload(Upload::new(contract_file)).await?;
 ///     Ok(())
 /// }
+///
+/// #[waterfall_test(backend = "canvas-ui")]
+/// async fn works_in_the_ui(mut canvas_ui: Box<dyn ContractsBackend>) -> Result<()> {
+///     …
+/// }
+///
+/// #[waterfall_test(chain = "snapshot")]
+/// async fn works_from_a_snapshot(mut canvas_ui: Box<dyn ContractsBackend>) -> Result<()> {
+///     …
+/// }
+///
+/// #[waterfall_test(timeout = "10m")]
+/// async fn takes_long(mut canvas_ui: Box<dyn ContractsBackend>) -> Result<()> {
+///     …
+/// }
 /// ```
 #[proc_macro_attribute]
-pub fn waterfall_test(_attr: TokenStream, item: TokenStream) -> TokenStream {
+pub fn waterfall_test(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = syn::parse_macro_input!(attr as syn::AttributeArgs);
     let item_fn =
         syn::parse2::<syn::ItemFn>(item.into()).expect("no item_fn can be parsed");
+    let args = Args::parse(&args);
+    let backend = match args.backend {
+        Some(backend) => quote! { #backend },
+        None => quote! { &crate::utils::backend::backend_from_env() },
+    };
+    let chain = match args.chain {
+        Some(chain) => quote! { #chain },
+        None => quote! { &crate::utils::canvas_node::chain_from_env() },
+    };
+    let timeout = match args.timeout {
+        Some(millis) => quote! { std::time::Duration::from_millis(#millis) },
+        None => quote! { crate::utils::timeout_from_env() },
+    };
+    let (backend_ident, backend_type) = backend_binding(&item_fn);
     let fn_name = &item_fn.sig.ident;
     let block = &item_fn.block;
     let fn_return_type = &item_fn.sig.output;
     let vis = &item_fn.vis;
     let attrs = &item_fn.attrs;
-    let ret = match fn_return_type {
-        syn::ReturnType::Default => quote! {},
-        syn::ReturnType::Type(rarrow, ret_type) => quote! { #rarrow #ret_type },
+    let (ret, ret_type) = match fn_return_type {
+        syn::ReturnType::Default => (quote! {}, quote! { () }),
+        syn::ReturnType::Type(rarrow, ret_type) => {
+            (quote! { #rarrow #ret_type }, quote! { #ret_type })
+        }
     };
     let res = quote! {
         #( #attrs )*
@@ -48,43 +103,165 @@ pub fn waterfall_test(_attr: TokenStream, item: TokenStream) -> TokenStream {
                 env_logger::init();
             });
 
-            // hack to get a timeout for async tests running.
-            // this is necessary so that the ci doesn't wait forever to fail, thus
-            // enabling faster feedback cycles.
-            let __orig_hook = std::panic::take_hook();
-            std::panic::set_hook(Box::new(move |panic_info| {
-                // invoke the default handler and exit the process
-                __orig_hook(panic_info);
-            }));
-            std::thread::spawn(|| {
-                let timeout: String = std::env::var("WATERFALL_TIMEOUT_SECS_PER_TEST")
-                    .unwrap_or(String::from("300")); // 5 * 60 = five minutes
-                let timeout: u64 = timeout.parse::<u64>()
-                    .expect("unable to parse WATERFALL_TEST_TIMEOUT into u64");
+            let __timeout = #timeout;
+            let __canvas_node = crate::utils::canvas_node::CanvasNode::start(#chain).await?;
+            let mut #backend_ident: #backend_type =
+                crate::utils::backend::new(#backend, __canvas_node.ws_url()).await?;
 
-                // We should get rid of this `sleep`
-                std::thread::sleep(std::time::Duration::from_secs(timeout));
+            // the test is cancelled if it doesn't finish in time. this is necessary so
+            // that the ci doesn't wait forever to fail, thus enabling faster feedback
+            // cycles.
+            let __outcome = futures_util::FutureExt::catch_unwind(
+                std::panic::AssertUnwindSafe(tokio::time::timeout(__timeout, async {
+                    let __ret: #ret_type = #block;
+                    __ret
+                })),
+            )
+            .await;
+            let __failed = match &__outcome {
+                Ok(Ok(__ret)) => crate::utils::artifacts::Outcome::is_failure(__ret),
+                _ => true,
+            };
+            if __failed {
+                crate::utils::artifacts::save(
+                    stringify!(#fn_name),
+                    &mut *#backend_ident,
+                    &__canvas_node,
+                )
+                .await;
+            }
+            let __shutdown = #backend_ident.shutdown().await;
+            match __outcome {
+                Ok(Ok(__ret)) => {
+                    __shutdown?;
+                    __ret
+                }
+                Ok(Err(_)) => {
+                    if let Err(err) = __shutdown {
+                        log::error!("shutdown after the timeout failed: {:?}", err);
+                    }
+                    panic!(
+                        "The test '{}' didn't finish within {:?} and was cancelled.\n\n\
+                        If this is no failure of UI-interaction you should consider \
+                        increasing `WATERFALL_TIMEOUT_SECS_PER_TEST` or the `timeout` \
+                        of the test",
+                        stringify!(#fn_name),
+                        __timeout
+                    );
+                }
+                Err(__panic) => {
+                    if let Err(err) = __shutdown {
+                        log::error!("shutdown after the panic failed: {:?}", err);
+                    }
+                    std::panic::resume_unwind(__panic)
+                }
+            }
+        }
+    };
+    res.into()
+}
 
-                std::process::Command::new("pkill")
-                    .args(&["-9", "-f", "geckodriver"])
-                    .output()
-                    .expect("can not execute pkill");
+/// The arguments which can be passed to `#[waterfall_test(…)]`.
+#[derive(Default)]
+struct Args {
+    /// The backend from `backend = "…"`.
+    backend: Option<String>,
+    /// The chain state from `chain = "…"`.
+    chain: Option<String>,
+    /// The timeout in milliseconds from `timeout = "…"`.
+    timeout: Option<u64>,
+}
 
-                panic!(
-                    "The test '{}' didn't finish in time and was killed.\n\n\
-                    If this is no failure of UI-interaction you should consider\
-                    increasing `WATERFALL_TIMEOUT_SECS_PER_TEST`",
-                    stringify!(#fn_name)
-                );
-            });
+impl Args {
+    /// Parses the arguments, panics on unknown ones.
+    fn parse(args: &[syn::NestedMeta]) -> Self {
+        let mut parsed = Self::default();
+        for arg in args {
+            match arg {
+                syn::NestedMeta::Meta(syn::Meta::NameValue(name_value)) => {
+                    let value = match &name_value.lit {
+                        syn::Lit::Str(value) => value.value(),
+                        _ => {
+                            panic!(
+                                "`{}` must be a string literal",
+                                name_value.path.to_token_stream()
+                            )
+                        }
+                    };
+                    if name_value.path.is_ident("backend") {
+                        parsed.backend = Some(value);
+                    } else if name_value.path.is_ident("chain") {
+                        parsed.chain = Some(value);
+                    } else if name_value.path.is_ident("timeout") {
+                        parsed.timeout = Some(parse_timeout(&value));
+                    } else {
+                        panic!(
+                            "unknown argument `{}` to `waterfall_test`",
+                            name_value.path.to_token_stream()
+                        )
+                    }
+                }
+                syn::NestedMeta::Meta(meta) => {
+                    panic!("unknown argument {:?} to `waterfall_test`", meta.path())
+                }
+                syn::NestedMeta::Lit(lit) => {
+                    panic!("unexpected literal {:?} in `waterfall_test`", lit)
+                }
+            }
+        }
+        parsed
+    }
+}
 
-            let mut canvas_ui = CanvasUi::new().await?;
-            let __ret = {
-                #block
-            };
-            canvas_ui.shutdown().await?;
-            __ret
+/// Parses a timeout like `120s` into milliseconds, panics if it is invalid.
+///
+/// Supported units are `ms`, `s`, `m` and `h`.
+fn parse_timeout(timeout: &str) -> u64 {
+    let unit_start = timeout
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or_else(|| timeout.len());
+    let (amount, unit) = timeout.split_at(unit_start);
+    let amount: u64 = amount.parse().unwrap_or_else(|_| {
+        panic!("invalid timeout {:?}, expected e.g. \"120s\"", timeout)
+    });
+    let factor = match unit.trim() {
+        "ms" => 1,
+        "s" => 1_000,
+        "m" => 60 * 1_000,
+        "h" => 60 * 60 * 1_000,
+        _ => {
+            panic!(
+                "invalid unit in timeout {:?}, expected one of `ms`, `s`, `m` or `h`",
+                timeout
+            )
         }
     };
-    res.into()
+    amount
+        .checked_mul(factor)
+        .unwrap_or_else(|| panic!("timeout {:?} is too large", timeout))
+}
+
+/// Returns the name and type of the first function argument, which the backend is
+/// bound to.
+///
+/// Falls back to `canvas_ui: Box<dyn ContractsBackend>` if the function has no
+/// arguments.
+fn backend_binding(item_fn: &syn::ItemFn) -> (syn::Ident, syn::Type) {
+    match item_fn.sig.inputs.first() {
+        Some(syn::FnArg::Typed(pat_type)) => {
+            match &*pat_type.pat {
+                syn::Pat::Ident(pat_ident) => {
+                    (pat_ident.ident.clone(), (*pat_type.ty).clone())
+                }
+                pat => panic!("unsupported argument pattern {:?}", pat),
+            }
+        }
+        Some(syn::FnArg::Receiver(_)) => panic!("`self` is not supported"),
+        None => {
+            (
+                syn::Ident::new("canvas_ui", proc_macro2::Span::call_site()),
+                syn::parse_quote! { Box<dyn crate::utils::backend::ContractsBackend> },
+            )
+        }
+    }
 }
@@ -16,24 +16,32 @@
 
 use crate::utils::{
     self,
-    canvas_ui::{
+    account::Account,
+    backend::{
         Call,
-        CanvasUi,
+        ContractsBackend,
         Error,
         Upload,
     },
+    balance::Balance,
     cargo_contract,
+    event_assertions::EventMatcher,
+    events::FieldValue,
 };
 use lang_macro::waterfall_test;
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
 #[waterfall_test]
-async fn contract_terminate_works(mut canvas_ui: CanvasUi) -> Result<()> {
+async fn contract_terminate_works(
+    mut canvas_ui: Box<dyn ContractsBackend>,
+) -> Result<()> {
     // given
     let manifest_path = utils::example_path("contract-terminate/Cargo.toml");
-    let contract_file =
-        cargo_contract::build(&manifest_path).expect("contract build failed");
+    let contract_file = cargo_contract::build(&manifest_path)
+        .await
+        .expect("contract build failed")
+        .contract_bundle;
     let contract_addr = canvas_ui.execute_upload(Upload::new(contract_file)).await?;
 
     // when
@@ -41,19 +49,35 @@ async fn contract_terminate_works(mut canvas_ui: CanvasUi) -> Result<()> {
         .execute_transaction(Call::new(&contract_addr, "terminate_me").max_gas("5000"))
         .await
         .expect("failed to execute transaction");
-    assert!(events.contains("system.KilledAccount"));
-    assert!(events.contains("balances.Transfer"));
-    assert!(events.contains("contracts.CodeRemoved"));
-    assert!(events.contains("contracts.Terminated"));
+    let alice = Account::alice().address();
+    events
+        .assert()
+        .contains("system.KilledAccount")
+        .contains("contracts.CodeRemoved")
+        .contains("contracts.Terminated")
+        .count("balances.Transfer", 1)
+        .contains_matching(
+            EventMatcher::new("balances.Transfer")
+                .field("from", FieldValue::Account(contract_addr.clone()))
+                .field("to", FieldValue::Account(alice))
+                .field_where("value", |value| {
+                    value.as_balance() > Some(Balance::default())
+                }),
+        );
 
     // then
     let err = canvas_ui
         .execute_transaction(Call::new(&contract_addr, "terminate_me").max_gas("5000"))
         .await
         .expect_err("successfully executed transaction, but expected it to_fail");
+    err.events()
+        .expect("no events for the failed transaction")
+        .assert()
+        .contains_exactly(&["system.ExtrinsicFailed"]);
     match err {
-        Error::ExtrinsicFailed(events) => {
-            assert!(events.contains("contracts.NotCallable"))
+        Error::ExtrinsicFailed { dispatch_error, .. } => {
+            assert_eq!(dispatch_error.module, "contracts");
+            assert_eq!(dispatch_error.error, "NotCallable");
         }
         err => panic!("encountered unexpected {:?}", err),
     }
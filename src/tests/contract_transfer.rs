@@ -16,60 +16,122 @@
 
 use crate::utils::{
     self,
-    canvas_ui::{
+    account::Account,
+    backend::{
         Call,
-        CanvasUi,
+        ContractsBackend,
         Upload,
     },
+    balance::{
+        Balance,
+        Unit,
+    },
     cargo_contract,
+    event_assertions::EventMatcher,
+    events::FieldValue,
 };
 use lang_macro::waterfall_test;
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
 #[waterfall_test]
-#[ignore] // until https://github.com/polkadot-js/apps/issues/5316 is fixed
-async fn contract_must_transfer_value_to_sender(mut canvas_ui: CanvasUi) -> Result<()> {
+async fn contract_must_transfer_value_to_sender(
+    mut canvas_ui: Box<dyn ContractsBackend>,
+) -> Result<()> {
     // given
     let manifest_path = utils::example_path("contract-transfer/Cargo.toml");
-    let contract_file =
-        cargo_contract::build(&manifest_path).expect("contract build failed");
+    let contract_file = cargo_contract::build(&manifest_path)
+        .await
+        .expect("contract build failed")
+        .contract_bundle;
     let contract_addr = canvas_ui.execute_upload(Upload::new(contract_file)).await?;
-    let balance_before = canvas_ui.balance_postfix("BOB".to_string()).await?;
+    let bob = Account::bob();
+    let balance_before = canvas_ui.balance(&bob).await?;
 
     // when
-    let _events = canvas_ui
+    let events = canvas_ui
         .execute_transaction(
             Call::new(&contract_addr, "give_me")
                 .push_value("value", "100")
-                .caller("BOB"),
+                .caller(&bob),
         )
         .await
         .expect("failed to execute transaction");
 
     // then
-    let balance_after = canvas_ui.balance_postfix("BOB".to_string()).await?;
-    assert_eq!(balance_after - balance_before, 1);
+    events.assert().contains_matching(
+        EventMatcher::new("balances.Transfer")
+            .field("from", FieldValue::Account(contract_addr.clone()))
+            .field("to", FieldValue::Account(bob.address()))
+            .field("value", FieldValue::Balance(Balance::units(100))),
+    );
+    // Bob receives the value, but pays the fees for the transaction
+    let balance_after = canvas_ui.balance(&bob).await?;
+    assert!(balance_after.free > balance_before.free);
+    assert!(balance_after.free < balance_before.free + Balance::units(100).plancks());
+    assert_eq!(balance_after.reserved, balance_before.reserved);
     Ok(())
 }
 
 #[waterfall_test]
-#[ignore] // until https://github.com/polkadot-js/apps/issues/5316 is fixed
-async fn transfer_exactly_ten_to_contract(mut canvas_ui: CanvasUi) -> Result<()> {
+async fn contract_must_receive_endowment(
+    mut canvas_ui: Box<dyn ContractsBackend>,
+) -> Result<()> {
     // given
     let manifest_path = utils::example_path("contract-transfer/Cargo.toml");
-    let contract_file =
-        cargo_contract::build(&manifest_path).expect("contract build failed");
+    let contract_file = cargo_contract::build(&manifest_path)
+        .await
+        .expect("contract build failed")
+        .contract_bundle;
+
+    // when
+    // the endowment is entered in `Kilo`, so the unit has to be chosen in the UI
+    let contract_addr = canvas_ui
+        .execute_upload(
+            Upload::new(contract_file).endowment(Balance::new(100, Unit::Kilo)),
+        )
+        .await?;
+
+    // then
+    let contract = Account::from_address(&contract_addr)?;
+    let balance = Balance::from_plancks(canvas_ui.balance(&contract).await?.total());
+    // the balance is compared with a margin, so that deposits or rent which the
+    // runtime takes from or adds to the contract don't break the test. The wrong
+    // unit would be off by a factor of 1000.
+    assert!(
+        balance > Balance::new(99, Unit::Kilo) && balance < Balance::new(101, Unit::Kilo),
+        "the contract holds {}, but was endowed with {}",
+        balance,
+        Balance::new(100, Unit::Kilo)
+    );
+    Ok(())
+}
+
+#[waterfall_test]
+async fn transfer_exactly_ten_to_contract(
+    mut canvas_ui: Box<dyn ContractsBackend>,
+) -> Result<()> {
+    // given
+    let manifest_path = utils::example_path("contract-transfer/Cargo.toml");
+    let contract_file = cargo_contract::build(&manifest_path)
+        .await
+        .expect("contract build failed")
+        .contract_bundle;
     let contract_addr = canvas_ui.execute_upload(Upload::new(contract_file)).await?;
 
     // when
     let result = canvas_ui
         .execute_transaction(
-            Call::new(&contract_addr, "was_it_ten").payment("10", "pico"),
+            Call::new(&contract_addr, "was_it_ten").payment(Balance::new(10, Unit::Pico)),
         )
         .await;
 
     // then
-    assert!(result.is_ok());
+    let events = result.expect("failed to execute transaction");
+    events.assert().contains_matching(
+        EventMatcher::new("balances.Transfer")
+            .field("to", FieldValue::Account(contract_addr))
+            .field("value", FieldValue::Balance(Balance::new(10, Unit::Pico))),
+    );
     Ok(())
 }
@@ -16,51 +16,67 @@
 
 use crate::utils::{
     self,
-    canvas_ui::{
+    backend::{
         Call,
-        CanvasUi,
+        ContractsBackend,
         Upload,
     },
+    balance::{
+        Balance,
+        Unit,
+    },
     cargo_contract,
-    extract_hash_from_contract_bundle,
+    value::Value,
 };
 use lang_macro::waterfall_test;
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
 #[waterfall_test]
-async fn delegator_works(mut canvas_ui: CanvasUi) -> Result<()> {
+async fn delegator_works(mut canvas_ui: Box<dyn ContractsBackend>) -> Result<()> {
     // given
     let accumulator_path =
         cargo_contract::build(&utils::example_path("delegator/accumulator/Cargo.toml"))
-            .expect("accumulator build failed");
-    let accumulator_hash = extract_hash_from_contract_bundle(&accumulator_path);
+            .await
+            .expect("accumulator build failed")
+            .contract_bundle;
 
     let adder_path =
         cargo_contract::build(&utils::example_path("delegator/adder/Cargo.toml"))
-            .expect("adder build failed");
-    let adder_hash = extract_hash_from_contract_bundle(&adder_path);
+            .await
+            .expect("adder build failed")
+            .contract_bundle;
 
     let subber_path =
         cargo_contract::build(&utils::example_path("delegator/subber/Cargo.toml"))
-            .expect("subber build failed");
-    let subber_hash = extract_hash_from_contract_bundle(&subber_path);
+            .await
+            .expect("subber build failed")
+            .contract_bundle;
 
     let delegator_path =
         cargo_contract::build(&utils::example_path("delegator/Cargo.toml"))
-            .expect("delegator build failed");
+            .await
+            .expect("delegator build failed")
+            .contract_bundle;
 
-    let _accumulator_addr = canvas_ui
-        .execute_upload(Upload::new(accumulator_path))
+    let accumulator_hash = canvas_ui
+        .execute_code_upload(Upload::new(accumulator_path))
+        .await?;
+    let adder_hash = canvas_ui
+        .execute_code_upload(Upload::new(adder_path))
+        .await?;
+    let subber_hash = canvas_ui
+        .execute_code_upload(Upload::new(subber_path))
         .await?;
-    let _adder_addr = canvas_ui.execute_upload(Upload::new(adder_path)).await?;
-    let _subber_addr = canvas_ui.execute_upload(Upload::new(subber_path)).await?;
 
     // when
+    // the arguments besides the code hashes keep their default values, e.g. the
+    // initial value `0`
     let delegator_addr = canvas_ui
         .execute_upload(
             Upload::new(delegator_path)
-                .endowment("100000", "Unit")
+                .default_values()
+                .endowment(Balance::new(100, Unit::Kilo))
                 .push_initial_value("accumulatorCodeHash", &accumulator_hash)
                 .push_initial_value("adderCodeHash", &adder_hash)
                 .push_initial_value("subberCodeHash", &subber_hash),
@@ -73,8 +89,10 @@ async fn delegator_works(mut canvas_ui: CanvasUi) -> Result<()> {
     assert_eq!(
         canvas_ui
             .execute_rpc(Call::new(&delegator_addr, "get").max_gas("5000"))
-            .await?,
-        "0"
+            .await?
+            .value()
+            .and_then(Value::as_i128),
+        Some(0)
     );
     canvas_ui
         .execute_transaction(
@@ -87,8 +105,10 @@ async fn delegator_works(mut canvas_ui: CanvasUi) -> Result<()> {
     assert_eq!(
         canvas_ui
             .execute_rpc(Call::new(&delegator_addr, "get").max_gas("5000"))
-            .await?,
-        "13"
+            .await?
+            .value()
+            .and_then(Value::as_i128),
+        Some(13)
     );
     canvas_ui
         .execute_transaction(Call::new(&delegator_addr, "switch").max_gas("5000"))
@@ -105,8 +125,10 @@ async fn delegator_works(mut canvas_ui: CanvasUi) -> Result<()> {
     assert_eq!(
         canvas_ui
             .execute_rpc(Call::new(&delegator_addr, "get").max_gas("5000"))
-            .await?,
-        "10"
+            .await?
+            .value()
+            .and_then(Value::as_i128),
+        Some(10)
     );
     Ok(())
 }
@@ -16,64 +16,224 @@
 
 use crate::utils::{
     self,
-    canvas_ui::{
+    account::Account,
+    backend::{
         Call,
-        CanvasUi,
+        ContractsBackend,
         Upload,
     },
+    balance::Balance,
     cargo_contract,
+    event_assertions::EventMatcher,
+    events::FieldValue,
+    value::Value,
 };
 use lang_macro::waterfall_test;
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
 #[waterfall_test]
-async fn erc20(mut canvas_ui: CanvasUi) -> Result<()> {
+async fn erc20(mut canvas_ui: Box<dyn ContractsBackend>) -> Result<()> {
     // given
     let manifest_path = utils::example_path("erc20/Cargo.toml");
-    let contract_file =
-        cargo_contract::build(&manifest_path).expect("contract build failed");
+    let contract_file = cargo_contract::build(&manifest_path)
+        .await
+        .expect("contract build failed")
+        .contract_bundle;
 
+    let alice = Account::alice();
+    let bob = Account::bob();
     let contract_addr = canvas_ui
         .execute_upload(
             Upload::new(contract_file)
-                .caller("BOB")
+                .caller(&bob)
                 .push_initial_value("initialSupply", "1000"),
         )
         .await?;
     assert_eq!(
         canvas_ui
             .execute_rpc(Call::new(&contract_addr, "total_supply"))
-            .await?,
-        "1000000000000000"
+            .await?
+            .value()
+            .and_then(Value::as_u128),
+        Some(Balance::units(1000).plancks())
     );
     assert_eq!(
         canvas_ui
             .execute_rpc(
-                Call::new(&contract_addr, "balance_of").push_value("owner", "bob")
+                Call::new(&contract_addr, "balance_of").push_account("owner", &bob)
             )
-            .await?,
-        "1000000000000000"
+            .await?
+            .value()
+            .and_then(Value::as_u128),
+        Some(Balance::units(1000).plancks())
     );
 
-    canvas_ui
+    let events = canvas_ui
         .execute_transaction(
             Call::new(&contract_addr, "transfer")
-                .caller("BOB")
-                .push_value("to", "ALICE")
+                .caller(&bob)
+                .push_account("to", &alice)
                 .push_value("value", "500"),
         )
         .await
         .expect("failed to execute transaction");
+    events
+        .assert()
+        .does_not_contain("system.ExtrinsicFailed")
+        .contains_in_order(&[
+            "contracts.ContractEmitted",
+            "erc20.Transfer",
+            "system.ExtrinsicSuccess",
+        ])
+        .count("erc20.Transfer", 1);
+    let transfer = events
+        .find("erc20.Transfer")
+        .expect("no `erc20.Transfer` event");
+    assert_eq!(
+        transfer.field("from").and_then(FieldValue::as_account),
+        Some(bob.address().as_str())
+    );
+    assert_eq!(
+        transfer.field("to").and_then(FieldValue::as_account),
+        Some(alice.address().as_str())
+    );
+    assert_eq!(
+        transfer.field("value").and_then(FieldValue::as_balance),
+        Some(Balance::units(500))
+    );
 
     assert_eq!(
         canvas_ui
             .execute_rpc(
-                Call::new(&contract_addr, "balance_of").push_value("owner", "ALICE")
+                Call::new(&contract_addr, "balance_of").push_account("owner", &alice)
             )
-            .await?,
-        "500000000000000"
+            .await?
+            .value()
+            .and_then(Value::as_u128),
+        Some(Balance::units(500).plancks())
     );
 
     Ok(())
 }
+
+#[waterfall_test]
+async fn erc20_transfer_from_new_account_without_tokens(
+    mut canvas_ui: Box<dyn ContractsBackend>,
+) -> Result<()> {
+    // given
+    let manifest_path = utils::example_path("erc20/Cargo.toml");
+    let contract_file = cargo_contract::build(&manifest_path)
+        .await
+        .expect("contract build failed")
+        .contract_bundle;
+    let contract_addr = canvas_ui
+        .execute_upload(
+            Upload::new(contract_file).push_initial_value("initialSupply", "1000"),
+        )
+        .await?;
+    let alice = Account::alice();
+    let dan = canvas_ui
+        .create_account(
+            "dan",
+            "bottom drive obey lake curtain smoke basket hold race lonely fit walk",
+        )
+        .await?;
+    canvas_ui
+        .fund_account(&dan, &alice, Balance::units(100))
+        .await?
+        .assert()
+        .contains("balances.Transfer");
+
+    // when
+    let events = canvas_ui
+        .execute_transaction(
+            Call::new(&contract_addr, "transfer")
+                .caller(&dan)
+                .push_account("to", &alice)
+                .push_value("value", "1"),
+        )
+        .await
+        .expect("failed to execute transaction");
+
+    // then
+    events
+        .assert()
+        .contains("system.ExtrinsicSuccess")
+        .does_not_contain("erc20.Transfer");
+    assert_eq!(
+        canvas_ui
+            .execute_rpc(
+                Call::new(&contract_addr, "balance_of").push_account("owner", &dan)
+            )
+            .await?
+            .value()
+            .and_then(Value::as_u128),
+        Some(0)
+    );
+    Ok(())
+}
+
+#[waterfall_test(backend = "canvas-ui")]
+async fn erc20_transfer_from_imported_account(
+    mut canvas_ui: Box<dyn ContractsBackend>,
+) -> Result<()> {
+    // given
+    let manifest_path = utils::example_path("erc20/Cargo.toml");
+    let contract_file = cargo_contract::build(&manifest_path)
+        .await
+        .expect("contract build failed")
+        .contract_bundle;
+    let contract_addr = canvas_ui
+        .execute_upload(
+            Upload::new(contract_file).push_initial_value("initialSupply", "1000"),
+        )
+        .await?;
+    let alice = Account::alice();
+    // the key pair in the keystore is encrypted, only the UI is able to sign with it
+    let erin = canvas_ui
+        .import_account(&utils::fixture_path("erin.json"), "waterfall")
+        .await?;
+    canvas_ui
+        .fund_account(&erin, &alice, Balance::units(100))
+        .await?
+        .assert()
+        .contains("balances.Transfer");
+    canvas_ui
+        .execute_transaction(
+            Call::new(&contract_addr, "transfer")
+                .push_account("to", &erin)
+                .push_value("value", "10"),
+        )
+        .await
+        .expect("failed to execute transaction");
+
+    // when
+    let events = canvas_ui
+        .execute_transaction(
+            Call::new(&contract_addr, "transfer")
+                .caller(&erin)
+                .push_account("to", &alice)
+                .push_value("value", "3"),
+        )
+        .await
+        .expect("failed to execute transaction");
+
+    // then
+    events.assert().contains_matching(
+        EventMatcher::new("erc20.Transfer")
+            .field("from", FieldValue::Account(erin.address()))
+            .field("to", FieldValue::Account(alice.address())),
+    );
+    assert_eq!(
+        canvas_ui
+            .execute_rpc(
+                Call::new(&contract_addr, "balance_of").push_account("owner", &erin)
+            )
+            .await?
+            .value()
+            .and_then(Value::as_u128),
+        Some(Balance::units(7).plancks())
+    );
+    Ok(())
+}
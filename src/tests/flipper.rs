@@ -16,30 +16,39 @@
 
 use crate::utils::{
     self,
-    canvas_ui::{
+    backend::{
         Call,
-        CanvasUi,
+        ContractsBackend,
         Upload,
     },
     cargo_contract,
+    contract_bundle::ContractBundle,
+    value::Value,
 };
 use lang_macro::waterfall_test;
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
 #[waterfall_test]
-async fn flipper_works(mut canvas_ui: CanvasUi) -> Result<()> {
+async fn flipper_works(mut canvas_ui: Box<dyn ContractsBackend>) -> Result<()> {
     // given
     let manifest_path = utils::example_path("flipper/Cargo.toml");
-    let contract_file =
-        cargo_contract::build(&manifest_path).expect("contract build failed");
+    let contract_file = cargo_contract::build(&manifest_path)
+        .await
+        .expect("contract build failed")
+        .contract_bundle;
 
-    let contract_addr = canvas_ui.execute_upload(Upload::new(contract_file)).await?;
+    // `initValue` keeps its default `false`
+    let contract_addr = canvas_ui
+        .execute_upload(Upload::new(contract_file).default_values())
+        .await?;
     assert_eq!(
         canvas_ui
             .execute_rpc(Call::new(&contract_addr, "get"))
-            .await?,
-        "false"
+            .await?
+            .value()
+            .and_then(Value::as_bool),
+        Some(false)
     );
 
     // when
@@ -52,18 +61,22 @@ async fn flipper_works(mut canvas_ui: CanvasUi) -> Result<()> {
     assert_eq!(
         canvas_ui
             .execute_rpc(Call::new(&contract_addr, "get"))
-            .await?,
-        "true"
+            .await?
+            .value()
+            .and_then(Value::as_bool),
+        Some(true)
     );
     Ok(())
 }
 
 #[waterfall_test]
-async fn default_constructor(mut canvas_ui: CanvasUi) -> Result<()> {
+async fn default_constructor(mut canvas_ui: Box<dyn ContractsBackend>) -> Result<()> {
     // given
     let manifest_path = utils::example_path("flipper/Cargo.toml");
-    let contract_file =
-        cargo_contract::build(&manifest_path).expect("contract build failed");
+    let contract_file = cargo_contract::build(&manifest_path)
+        .await
+        .expect("contract build failed")
+        .contract_bundle;
 
     // when
     let contract_addr = canvas_ui
@@ -74,8 +87,56 @@ async fn default_constructor(mut canvas_ui: CanvasUi) -> Result<()> {
     assert_eq!(
         canvas_ui
             .execute_rpc(Call::new(&contract_addr, "get"))
-            .await?,
-        "false"
+            .await?
+            .value()
+            .and_then(Value::as_bool),
+        Some(false)
+    );
+    Ok(())
+}
+
+#[waterfall_test]
+async fn instantiate_from_code_hash(
+    mut canvas_ui: Box<dyn ContractsBackend>,
+) -> Result<()> {
+    // given
+    let manifest_path = utils::example_path("flipper/Cargo.toml");
+    let contract_file = cargo_contract::build(&manifest_path)
+        .await
+        .expect("contract build failed")
+        .contract_bundle;
+    let code_hash = ContractBundle::from_path(&contract_file)?
+        .code_hash()
+        .to_string();
+    let first_addr = canvas_ui
+        .execute_upload(Upload::new(contract_file.clone()).default_values())
+        .await?;
+
+    // when
+    let second_addr = canvas_ui
+        .execute_instantiate(
+            &code_hash,
+            Upload::new(contract_file).push_initial_value("initValue", "true"),
+        )
+        .await?;
+
+    // then
+    assert_ne!(first_addr, second_addr);
+    assert_eq!(
+        canvas_ui
+            .execute_rpc(Call::new(&first_addr, "get"))
+            .await?
+            .value()
+            .and_then(Value::as_bool),
+        Some(false)
+    );
+    assert_eq!(
+        canvas_ui
+            .execute_rpc(Call::new(&second_addr, "get"))
+            .await?
+            .value()
+            .and_then(Value::as_bool),
+        Some(true)
     );
     Ok(())
 }
@@ -16,12 +16,13 @@
 
 use crate::utils::{
     self,
-    canvas_ui::{
+    backend::{
         Call,
-        CanvasUi,
+        ContractsBackend,
         Upload,
     },
     cargo_contract,
+    value::Value,
 };
 use lang_macro::waterfall_test;
 
@@ -29,11 +30,13 @@ type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
 #[waterfall_test]
 #[ignore]
-async fn rand_extension(mut canvas_ui: CanvasUi) -> Result<()> {
+async fn rand_extension(mut canvas_ui: Box<dyn ContractsBackend>) -> Result<()> {
     // given
     let manifest_path = utils::example_path("rand-extension/Cargo.toml");
-    let contract_file =
-        cargo_contract::build(&manifest_path).expect("contract build failed");
+    let contract_file = cargo_contract::build(&manifest_path)
+        .await
+        .expect("contract build failed")
+        .contract_bundle;
     let contract_addr = canvas_ui
         .execute_upload(
             Upload::new(contract_file).push_initial_value("initValue", "0x00"),
@@ -41,25 +44,27 @@ async fn rand_extension(mut canvas_ui: CanvasUi) -> Result<()> {
         .await?;
 
     // when
-    assert_eq!(
-        canvas_ui
-            .execute_rpc(Call::new(&contract_addr, "get"))
-            .await
-            .expect("failed to execute transaction"),
-        "0x000000…00000000"
-    );
+    let outcome = canvas_ui
+        .execute_rpc(Call::new(&contract_addr, "get"))
+        .await
+        .expect("failed to execute transaction");
+    assert_eq!(outcome.value(), Some(&Value::Bytes(vec![0; 32])));
+    assert_eq!(outcome.text(), "0x000000…00000000");
     let _events = canvas_ui
         .execute_transaction(Call::new(&contract_addr, "update"))
         .await
         .expect("failed to execute transaction");
 
     // then
-    assert_ne!(
-        canvas_ui
-            .execute_rpc(Call::new(&contract_addr, "get"))
-            .await
-            .expect("failed to execute transaction"),
-        "0x000000…00000000"
-    );
+    let outcome = canvas_ui
+        .execute_rpc(Call::new(&contract_addr, "get"))
+        .await
+        .expect("failed to execute transaction");
+    let random = outcome
+        .value()
+        .and_then(Value::as_bytes)
+        .expect("the return value is no byte array");
+    assert_eq!(random.len(), 32);
+    assert_ne!(random, &[0; 32][..]);
     Ok(())
 }
@@ -0,0 +1,202 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The accounts which call contracts, pay for transactions and hold balances.
+//!
+//! The dev accounts Alice through Ferdie exist on the `canvas-node` and in the
+//! `canvas-ui`, which names them in lowercase (e.g. `alice`). Custom accounts
+//! are created from a mnemonic phrase or a seed, or imported from the JSON
+//! keystore which the UI exports.
+
+use serde::Deserialize;
+use sp_keyring::AccountKeyring;
+use std::{
+    fmt,
+    fs::File,
+    path::Path,
+};
+use subxt::{
+    sp_core::{
+        crypto::{
+            Pair as _,
+            Ss58Codec,
+        },
+        sr25519,
+    },
+    sp_runtime::AccountId32,
+};
+
+/// The password with which the UI stores the accounts created by the tests.
+pub const DEFAULT_PASSWORD: &str = "waterfall";
+
+/// An account together with what is needed to sign its transactions.
+#[derive(Clone)]
+pub struct Account {
+    /// The name under which the UI lists the account.
+    name: String,
+    account_id: AccountId32,
+    /// The key pair, unknown for accounts imported from an encrypted keystore.
+    pair: Option<sr25519::Pair>,
+    /// The password with which the UI unlocks the account for signing, the dev
+    /// accounts have none.
+    password: Option<String>,
+}
+
+/// The fields of a JSON keystore which are stored unencrypted.
+#[derive(Deserialize)]
+struct Keystore {
+    address: String,
+    meta: KeystoreMeta,
+}
+
+#[derive(Deserialize)]
+struct KeystoreMeta {
+    name: Option<String>,
+}
+
+impl Account {
+    /// The dev account Alice, which is preselected in the UI.
+    pub fn alice() -> Self {
+        Self::dev(AccountKeyring::Alice)
+    }
+
+    /// The dev account Bob.
+    pub fn bob() -> Self {
+        Self::dev(AccountKeyring::Bob)
+    }
+
+    /// Returns the dev account with `name` (e.g. `ALICE` or `bob`).
+    pub fn from_name(name: &str) -> Option<Self> {
+        let keyring = match name.to_lowercase().as_str() {
+            "alice" => AccountKeyring::Alice,
+            "bob" => AccountKeyring::Bob,
+            "charlie" => AccountKeyring::Charlie,
+            "dave" => AccountKeyring::Dave,
+            "eve" => AccountKeyring::Eve,
+            "ferdie" => AccountKeyring::Ferdie,
+            _ => return None,
+        };
+        Some(Self::dev(keyring))
+    }
+
+    fn dev(keyring: AccountKeyring) -> Self {
+        Self {
+            name: format!("{:?}", keyring).to_lowercase(),
+            account_id: keyring.to_account_id(),
+            pair: Some(keyring.pair()),
+            password: None,
+        }
+    }
+
+    /// Returns the account `name` for `suri`, which is either a mnemonic phrase or
+    /// a hex encoded seed. The UI stores it with the `DEFAULT_PASSWORD`.
+    pub fn from_suri(name: &str, suri: &str) -> Result<Self, String> {
+        let pair = sr25519::Pair::from_string(suri, None).map_err(|err| {
+            format!("invalid mnemonic or seed for {:?}: {:?}", name, err)
+        })?;
+        Ok(Self {
+            name: name.to_string(),
+            account_id: pair.public().into(),
+            pair: Some(pair),
+            password: Some(DEFAULT_PASSWORD.to_string()),
+        })
+    }
+
+    /// Returns the account of the JSON keystore at `path`, which is unlocked with
+    /// `password`.
+    ///
+    /// The key pair is encrypted in the keystore, so only the UI is able to sign
+    /// for the account.
+    pub fn from_keystore(path: &Path, password: &str) -> Result<Self, String> {
+        let file = File::open(path)
+            .map_err(|err| format!("unable to open the keystore {:?}: {}", path, err))?;
+        let Keystore { address, meta } = serde_json::from_reader(file)
+            .map_err(|err| format!("unable to parse the keystore {:?}: {}", path, err))?;
+        let account_id = AccountId32::from_ss58check(&address)
+            .map_err(|err| format!("invalid address {:?}: {:?}", address, err))?;
+        Ok(Self {
+            // the UI lists accounts without a name by their address
+            name: meta.name.unwrap_or(address),
+            account_id,
+            pair: None,
+            password: Some(password.to_string()),
+        })
+    }
+
+    /// Returns the account with the SS58 `address`, e.g. of a contract, named
+    /// after its address.
+    ///
+    /// Its key pair is unknown, so it can only be used to read its balance or as
+    /// an argument.
+    pub fn from_address(address: &str) -> Result<Self, String> {
+        let account_id = AccountId32::from_ss58check(address)
+            .map_err(|err| format!("invalid address {:?}: {:?}", address, err))?;
+        Ok(Self {
+            name: address.to_string(),
+            account_id,
+            pair: None,
+            password: None,
+        })
+    }
+
+    /// Returns the name under which the UI lists the account.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the SS58 address of the account.
+    pub fn address(&self) -> String {
+        self.account_id().to_ss58check()
+    }
+
+    /// Returns the id of the account on the chain.
+    pub fn account_id(&self) -> AccountId32 {
+        self.account_id.clone()
+    }
+
+    /// Returns the key pair which signs the transactions of the account, `None`
+    /// if it is only known to the UI.
+    pub fn pair(&self) -> Option<&sr25519::Pair> {
+        self.pair.as_ref()
+    }
+
+    /// Returns the password with which the UI unlocks the account for signing.
+    pub fn password(&self) -> Option<&str> {
+        self.password.as_deref()
+    }
+}
+
+impl PartialEq for Account {
+    fn eq(&self, other: &Self) -> bool {
+        self.account_id == other.account_id
+    }
+}
+
+impl Eq for Account {}
+
+/// Formats the account as its name and address, the secrets are omitted.
+impl fmt::Debug for Account {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Account")
+            .field("name", &self.name)
+            .field("address", &self.address())
+            .finish()
+    }
+}
+
+impl fmt::Display for Account {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
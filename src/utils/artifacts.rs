@@ -0,0 +1,74 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Artifacts which are saved when a test fails, to make the failure diagnosable
+//! (especially in the CI).
+
+use super::{
+    backend::ContractsBackend,
+    canvas_node::CanvasNode,
+};
+use std::{
+    fs,
+    path::PathBuf,
+};
+
+/// The outcome of a test, which is either a failure or not.
+pub trait Outcome {
+    /// Returns `true` if the test failed.
+    fn is_failure(&self) -> bool;
+}
+
+impl Outcome for () {
+    fn is_failure(&self) -> bool {
+        false
+    }
+}
+
+impl<T, E> Outcome for Result<T, E> {
+    fn is_failure(&self) -> bool {
+        self.is_err()
+    }
+}
+
+/// Returns the directory which the artifacts of `test` are saved to.
+///
+/// The directory is `artifacts/<test>` by default, the base directory can be
+/// overridden with the environment variable `WATERFALL_ARTIFACTS_DIR`.
+pub fn dir(test: &str) -> PathBuf {
+    let base = std::env::var("WATERFALL_ARTIFACTS_DIR")
+        .unwrap_or_else(|_| String::from("artifacts"));
+    PathBuf::from(base).join(test)
+}
+
+/// Saves the artifacts of the failed `test`.
+///
+/// This is the state of the `backend` (e.g. a screenshot of the browser) and the
+/// log of the `node`. Failing to save an artifact is logged, but doesn't fail.
+pub async fn save(test: &str, backend: &mut dyn ContractsBackend, node: &CanvasNode) {
+    let dir = dir(test);
+    log::error!("the test {:?} failed, saving artifacts to {:?}", test, dir);
+    if let Err(err) = fs::create_dir_all(&dir) {
+        log::error!("unable to create {:?}: {:?}", dir, err);
+        return
+    }
+    if let Err(err) = backend.save_failure_artifacts(&dir).await {
+        log::error!("unable to save the artifacts of the backend: {:?}", err);
+    }
+    if let Some(log_path) = node.log_path() {
+        if let Err(err) = fs::copy(log_path, dir.join("canvas-node.log")) {
+            log::error!("unable to copy the node log {:?}: {:?}", log_path, err);
+        }
+    }
+}
@@ -0,0 +1,483 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+pub use super::events::{
+    DispatchError,
+    Event,
+    Events,
+};
+
+use super::{
+    account::Account,
+    balance::{
+        AccountBalance,
+        Balance,
+    },
+    canvas_ui::CanvasUi,
+    contract_bundle::BundleError,
+    node_rpc::NodeRpc,
+    value::Value,
+    wait::WaitError,
+};
+use async_trait::async_trait;
+use fantoccini::error::CmdError;
+use std::{
+    fmt,
+    path::{
+        Path,
+        PathBuf,
+    },
+    time::Duration,
+};
+
+/// The backend which is used if neither `#[waterfall_test(backend = "…")]` nor the
+/// environment variable `WATERFALL_BACKEND` specify one.
+const DEFAULT_BACKEND: &str = "canvas-ui";
+
+/// The operations a backend has to support in order to run the waterfall tests.
+///
+/// The tests are written against this trait, so that the same scenario can be
+/// executed against the `canvas-ui` as well as against any other backend.
+#[async_trait(?Send)]
+pub trait ContractsBackend {
+    /// Uploads and instantiates the contract described by `upload`.
+    ///
+    /// Returns the address of the instantiated contract.
+    async fn execute_upload(&mut self, upload: Upload) -> Result<String, Error>;
+
+    /// Instantiates the code `code_hash`, which is already stored on the chain,
+    /// with the constructor, initial values, endowment and caller of `upload`.
+    ///
+    /// The contract bundle of `upload` only describes the code, it is not uploaded
+    /// again. Returns the address of the instantiated contract.
+    async fn execute_instantiate(
+        &mut self,
+        code_hash: &str,
+        upload: Upload,
+    ) -> Result<String, Error>;
+
+    /// Stores the code of the contract described by `upload` on the chain and
+    /// returns its code hash, e.g. for `execute_instantiate`.
+    ///
+    /// The code is stored with `instantiate_with_code`, in the UI through the
+    /// "Upload & Instantiate" flow, and only its code hash is used afterwards.
+    /// The arguments of the constructor without an initial value keep their
+    /// default value.
+    async fn execute_code_upload(&mut self, upload: Upload) -> Result<String, Error>;
+
+    /// Executes the RPC call `call` and returns its outcome.
+    async fn execute_rpc(&mut self, call: Call) -> Result<RpcOutcome, Error>;
+
+    /// Executes the transaction `call` and returns the events it emitted.
+    async fn execute_transaction(&mut self, call: Call) -> Result<Events, Error>;
+
+    /// Returns the balance of `account` as read from the chain.
+    async fn balance(&mut self, account: &Account) -> Result<AccountBalance, Error>;
+
+    /// Creates the account `name` from `suri`, which is either a mnemonic phrase
+    /// or a hex encoded seed.
+    async fn create_account(&mut self, name: &str, suri: &str) -> Result<Account, Error>;
+
+    /// Imports the account from the JSON keystore at `path`, which is encrypted
+    /// with `password`.
+    async fn import_account(
+        &mut self,
+        path: &Path,
+        password: &str,
+    ) -> Result<Account, Error>;
+
+    /// Transfers `amount` from `from` to `account`, e.g. so that a new account is
+    /// able to pay the fees of its transactions.
+    ///
+    /// Returns the events of the transfer.
+    async fn fund_account(
+        &mut self,
+        account: &Account,
+        from: &Account,
+        amount: Balance,
+    ) -> Result<Events, Error>;
+
+    /// Releases the resources held by the backend at the end of a test.
+    async fn shutdown(&mut self) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Saves everything which helps to diagnose a failed test to `dir`.
+    ///
+    /// This is invoked by `#[waterfall_test]` before the shutdown of a failed test.
+    async fn save_failure_artifacts(
+        &mut self,
+        _dir: &Path,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+}
+
+/// Creates the backend with the name `backend`, which executes the tests against
+/// the node at `node_url`.
+///
+/// This is invoked by `#[waterfall_test]` at the start of each test.
+pub async fn new(
+    backend: &str,
+    node_url: &str,
+) -> Result<Box<dyn ContractsBackend>, Box<dyn std::error::Error>> {
+    log::info!("using backend {:?}", backend);
+    match backend {
+        "canvas-ui" => Ok(Box::new(CanvasUi::new(node_url).await?)),
+        "node-rpc" => Ok(Box::new(NodeRpc::new(node_url).await?)),
+        other => Err(format!("unknown backend {:?}", other).into()),
+    }
+}
+
+/// Returns the name of the backend which should be used for tests that don't
+/// specify one.
+///
+/// Defaults to `canvas-ui`, can be overridden with the environment variable
+/// `WATERFALL_BACKEND`. Available backends are `canvas-ui` and `node-rpc`.
+pub fn backend_from_env() -> String {
+    std::env::var("WATERFALL_BACKEND").unwrap_or_else(|_| DEFAULT_BACKEND.to_string())
+}
+
+/// The errors which the operations of a `ContractsBackend` return.
+///
+/// The variants tell what went wrong, so that tests can match on them, e.g. to
+/// assert that a message traps.
+#[derive(Debug)]
+pub enum Error {
+    /// No element matching `locator` was found in the UI.
+    ElementNotFound {
+        /// The CSS selector or XPath of the element.
+        locator: String,
+    },
+    /// A step didn't complete within its timeout.
+    Timeout {
+        /// What was waited for, e.g. `the 'Skip Intro' button`.
+        step: String,
+        /// How long was waited.
+        timeout: Duration,
+    },
+    /// The browser failed to open `url`.
+    Navigation { url: String, source: CmdError },
+    /// The extrinsic was included in a block, but its dispatch failed.
+    ExtrinsicFailed {
+        /// The error with which the dispatch failed.
+        dispatch_error: DispatchError,
+        /// The events which the extrinsic emitted.
+        events: Events,
+    },
+    /// The contract trapped, e.g. because of a `panic!` in a message. Holds the
+    /// events which the extrinsic emitted.
+    ContractTrapped(Events),
+    /// The execution ran out of gas. Holds the events which the extrinsic emitted.
+    OutOfGas(Events),
+    /// The contract bundle was rejected before it was instantiated, e.g. because
+    /// it is invalid or the initial values don't match its constructor.
+    UploadRejected(String),
+    /// The address of the instantiated contract couldn't be parsed from `url`.
+    AddressUnparseable { url: String },
+    /// The call doesn't match the metadata of the contract.
+    InvalidCall(String),
+    /// The events neither or both contain `system.ExtrinsicSuccess` and
+    /// `system.ExtrinsicFailed`.
+    AmbiguousOutcome(Events),
+    /// The operation is not supported by the backend or the chain.
+    Unsupported(String),
+    /// A WebDriver command failed.
+    WebDriver(CmdError),
+    /// Any other error, e.g. of the connection to the node.
+    Other(Box<dyn std::error::Error>),
+}
+
+impl Error {
+    /// Returns the error for an extrinsic which failed with `dispatch_error` and
+    /// emitted `events`.
+    ///
+    /// Traps and running out of gas get their own variants.
+    pub fn extrinsic_failed(dispatch_error: DispatchError, events: Events) -> Self {
+        match (
+            dispatch_error.module.as_str(),
+            dispatch_error.error.as_str(),
+        ) {
+            ("contracts", "ContractTrapped") => Error::ContractTrapped(events),
+            ("contracts", "OutOfGas") => Error::OutOfGas(events),
+            _ => {
+                Error::ExtrinsicFailed {
+                    dispatch_error,
+                    events,
+                }
+            }
+        }
+    }
+
+    /// Returns the events which the extrinsic emitted, if the error was caused
+    /// by a failed extrinsic.
+    pub fn events(&self) -> Option<&Events> {
+        match self {
+            Error::ExtrinsicFailed { events, .. }
+            | Error::ContractTrapped(events)
+            | Error::OutOfGas(events)
+            | Error::AmbiguousOutcome(events) => Some(events),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ElementNotFound { locator } => {
+                write!(f, "no element matching {:?} was found", locator)
+            }
+            Error::Timeout { step, timeout } => {
+                write!(f, "waited {:?} for {}, but it didn't happen", timeout, step)
+            }
+            Error::Navigation { url, source } => {
+                write!(f, "unable to open {:?}: {}", url, source)
+            }
+            Error::ExtrinsicFailed { dispatch_error, .. } => {
+                write!(f, "the extrinsic failed with {}", dispatch_error)?;
+                if !dispatch_error.docs.is_empty() {
+                    write!(f, ": {}", dispatch_error.docs)?;
+                }
+                Ok(())
+            }
+            Error::ContractTrapped(_) => write!(f, "the contract trapped"),
+            Error::OutOfGas(_) => write!(f, "the execution ran out of gas"),
+            Error::UploadRejected(reason) => {
+                write!(f, "the upload was rejected: {}", reason)
+            }
+            Error::AddressUnparseable { url } => {
+                write!(f, "unable to parse the contract address from {:?}", url)
+            }
+            Error::InvalidCall(reason) => write!(f, "invalid call: {}", reason),
+            Error::AmbiguousOutcome(events) => {
+                write!(
+                    f,
+                    "neither or both of `system.ExtrinsicSuccess` and \
+                    `system.ExtrinsicFailed` were emitted:\n{}",
+                    events
+                )
+            }
+            Error::Unsupported(reason) => write!(f, "unsupported: {}", reason),
+            Error::WebDriver(err) => write!(f, "{}", err),
+            Error::Other(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Navigation { source, .. } | Error::WebDriver(source) => Some(source),
+            Error::Other(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<CmdError> for Error {
+    fn from(err: CmdError) -> Self {
+        Error::WebDriver(err)
+    }
+}
+
+impl From<WaitError> for Error {
+    fn from(err: WaitError) -> Self {
+        match err {
+            WaitError::Timeout { what, timeout } => {
+                Error::Timeout {
+                    step: what,
+                    timeout,
+                }
+            }
+            WaitError::Cmd(err) => Error::WebDriver(err),
+        }
+    }
+}
+
+impl From<BundleError> for Error {
+    fn from(err: BundleError) -> Self {
+        Error::UploadRejected(err.to_string())
+    }
+}
+
+/// The outcome of an RPC call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RpcOutcome {
+    /// The return value, `None` if the message has no return type or if the
+    /// metadata of the contract is unknown.
+    value: Option<Value>,
+    /// The return value the way the UI displays it.
+    text: String,
+}
+
+impl RpcOutcome {
+    /// Creates a new `RpcOutcome` instance.
+    pub fn new(value: Option<Value>, text: String) -> Self {
+        Self { value, text }
+    }
+
+    /// Creates the outcome for the return value `value`, with the text the UI
+    /// displays for it.
+    pub fn from_value(value: Option<Value>) -> Self {
+        let text = match &value {
+            Some(value) => shorten_like_ui(value.to_string()),
+            None => String::new(),
+        };
+        Self { value, text }
+    }
+
+    /// Returns the return value of the message, decoded with its return type
+    /// from the metadata. `None` if the message has no return type, or if the
+    /// metadata of the contract is unknown.
+    pub fn value(&self) -> Option<&Value> {
+        self.value.as_ref()
+    }
+
+    /// Returns the return value the way the UI displays it, long values are
+    /// shortened, e.g. `0x000000…00000000`.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+/// Shortens long hex values the way the UI displays them, e.g.
+/// `0x000000…00000000`.
+fn shorten_like_ui(value: String) -> String {
+    let is_hex =
+        value.starts_with("0x") && value[2..].chars().all(|c| c.is_ascii_hexdigit());
+    // hex values are ASCII, so the slices are on char boundaries
+    if is_hex && value.len() > 18 {
+        format!("{}…{}", &value[..8], &value[value.len() - 8..])
+    } else {
+        value
+    }
+}
+
+pub struct Call {
+    /// Address of the contract.
+    pub(crate) contract_address: String,
+    /// Method to execute.
+    pub(crate) method: String,
+    /// Maximum gas allowed.
+    pub(crate) max_gas_allowed: Option<String>,
+    /// Values to pass along.
+    pub(crate) values: Vec<(String, String)>,
+    /// The payment to send with the call.
+    pub(crate) payment: Option<Balance>,
+    /// The account from which to execute the call.
+    pub(crate) caller: Option<Account>,
+}
+
+impl Call {
+    /// Creates a new `Transaction` instance.
+    pub fn new(contract_address: &str, method: &str) -> Self {
+        Self {
+            contract_address: contract_address.to_string(),
+            method: method.to_string(),
+            max_gas_allowed: None,
+            values: Vec::new(),
+            payment: None,
+            caller: None,
+        }
+    }
+
+    /// Adds an initial value.
+    pub fn push_value(mut self, key: &str, val: &str) -> Self {
+        self.values.push((key.to_string(), val.to_string()));
+        self
+    }
+
+    /// Adds the SS58 address of `account` as value of an `AccountId` argument.
+    pub fn push_account(self, key: &str, account: &Account) -> Self {
+        self.push_value(key, &account.address())
+    }
+
+    /// Sets the maximum gas allowed.
+    pub fn max_gas(mut self, max_gas: &str) -> Self {
+        self.max_gas_allowed = Some(max_gas.to_string());
+        self
+    }
+
+    /// Sets the payment submitted with the call.
+    pub fn payment(mut self, payment: Balance) -> Self {
+        self.payment = Some(payment);
+        self
+    }
+
+    /// Sets the account from which to execute the call.
+    pub fn caller(mut self, caller: &Account) -> Self {
+        self.caller = Some(caller.clone());
+        self
+    }
+}
+
+pub struct Upload {
+    /// Path to the contract which should be uploaded.
+    pub(crate) contract_path: PathBuf,
+    /// Values to instantiate the contract with.
+    pub(crate) initial_values: Vec<(String, String)>,
+    /// Whether arguments without an initial value keep their default value,
+    /// otherwise the upload is rejected.
+    pub(crate) default_values: bool,
+    /// Initial endowment of the contract.
+    pub(crate) endowment: Balance,
+    /// The constructor to use. If not specified the default selected one is used.
+    pub(crate) constructor: Option<String>,
+    /// The caller to use. If not specified the default selected one is used.
+    pub(crate) caller: Option<Account>,
+}
+
+impl Upload {
+    /// Creates a new `Upload` instance.
+    pub fn new(contract_path: PathBuf) -> Self {
+        Self {
+            contract_path,
+            initial_values: Vec::new(),
+            default_values: false,
+            endowment: Balance::units(1000),
+            constructor: None,
+            caller: None,
+        }
+    }
+
+    /// Adds an initial value.
+    pub fn push_initial_value(mut self, key: &str, val: &str) -> Self {
+        self.initial_values.push((key.to_string(), val.to_string()));
+        self
+    }
+
+    /// Lets the arguments of the constructor without an initial value keep their
+    /// default value, e.g. `false` for a `bool`.
+    pub fn default_values(mut self) -> Self {
+        self.default_values = true;
+        self
+    }
+
+    /// Sets the initial endowment.
+    pub fn endowment(mut self, endowment: Balance) -> Self {
+        self.endowment = endowment;
+        self
+    }
+
+    /// Sets the constructor to use for instantiation.
+    pub fn constructor(mut self, constructor: &str) -> Self {
+        self.constructor = Some(constructor.to_string());
+        self
+    }
+
+    /// Sets the caller to use for instantiation.
+    pub fn caller(mut self, caller: &Account) -> Self {
+        self.caller = Some(caller.clone());
+        self
+    }
+}
@@ -0,0 +1,326 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Balances of the `canvas-node` token and the units in which the `canvas-ui`
+//! takes and displays them.
+
+use std::fmt;
+
+/// The number of decimals of the `canvas-node` token.
+///
+/// The `canvas-node` doesn't set `tokenDecimals` in the properties of its chain
+/// spec, so the UI falls back to the polkadot-js default of 12 decimals (and to
+/// the default token name `Unit`). This has to be changed if a node with other
+/// properties is tested.
+pub const DECIMALS: u32 = 12;
+
+/// The SI units which the UI offers in the listboxes next to balance inputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)] // the UI calls the base unit `Unit`
+pub enum Unit {
+    Pico,
+    Nano,
+    Micro,
+    Milli,
+    Unit,
+    Kilo,
+    Mill,
+    Bill,
+    Tril,
+}
+
+impl Unit {
+    /// All units, from the smallest to the largest.
+    pub const ALL: [Unit; 9] = [
+        Unit::Pico,
+        Unit::Nano,
+        Unit::Micro,
+        Unit::Milli,
+        Unit::Unit,
+        Unit::Kilo,
+        Unit::Mill,
+        Unit::Bill,
+        Unit::Tril,
+    ];
+
+    /// Returns the name of the unit as it is shown in the UI, e.g. `pico`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Unit::Pico => "pico",
+            Unit::Nano => "nano",
+            Unit::Micro => "micro",
+            Unit::Milli => "milli",
+            Unit::Unit => "Unit",
+            Unit::Kilo => "Kilo",
+            Unit::Mill => "Mill",
+            Unit::Bill => "Bill",
+            Unit::Tril => "Tril",
+        }
+    }
+
+    /// Returns the unit with `name`, e.g. `pico`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|unit| unit.name() == name)
+    }
+
+    /// Returns the unit of the SI prefix (e.g. `k` in `kUnit`) with which the UI
+    /// displays balances.
+    fn from_prefix(prefix: char) -> Option<Self> {
+        match prefix {
+            'p' => Some(Unit::Pico),
+            'n' => Some(Unit::Nano),
+            'µ' => Some(Unit::Micro),
+            'm' => Some(Unit::Milli),
+            'k' => Some(Unit::Kilo),
+            'M' => Some(Unit::Mill),
+            'B' => Some(Unit::Bill),
+            'T' => Some(Unit::Tril),
+            _ => None,
+        }
+    }
+
+    /// Returns the number of plancks, i.e. the smallest unit of the token, in
+    /// one of this unit.
+    pub fn plancks(self) -> u128 {
+        let exponent = match self {
+            Unit::Pico => -12,
+            Unit::Nano => -9,
+            Unit::Micro => -6,
+            Unit::Milli => -3,
+            Unit::Unit => 0,
+            Unit::Kilo => 3,
+            Unit::Mill => 6,
+            Unit::Bill => 9,
+            Unit::Tril => 12,
+        };
+        10u128.pow((DECIMALS as i32 + exponent) as u32)
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// An exact balance of the `canvas-node` token, in plancks.
+///
+/// # Usage
+///
+/// ```no_compile
+/// let endowment = Balance::new(100, Unit::Kilo);
+/// assert_eq!(endowment.input(), ("100".to_string(), Unit::Kilo));
+/// assert_eq!(Balance::parse("1.5000 kUnit")?, Balance::new(1500, Unit::Unit));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Balance(u128);
+
+impl Balance {
+    /// Returns the balance of `amount` in `unit`.
+    ///
+    /// # Panics
+    ///
+    /// If the balance doesn't fit into a `u128`.
+    pub fn new(amount: u128, unit: Unit) -> Self {
+        let plancks = amount
+            .checked_mul(unit.plancks())
+            .unwrap_or_else(|| panic!("{} {} overflows u128", amount, unit));
+        Self(plancks)
+    }
+
+    /// Returns the balance of `amount` in `Unit`.
+    pub fn units(amount: u128) -> Self {
+        Self::new(amount, Unit::Unit)
+    }
+
+    /// Returns the balance of `plancks`, i.e. in the smallest unit of the token.
+    pub fn from_plancks(plancks: u128) -> Self {
+        Self(plancks)
+    }
+
+    /// Returns the balance in plancks, i.e. in the smallest unit of the token.
+    pub fn plancks(self) -> u128 {
+        self.0
+    }
+
+    /// Returns the text to enter into a balance input of the UI and the unit to
+    /// choose next to it.
+    ///
+    /// The largest unit in which the balance is a whole number is chosen, since
+    /// the inputs don't take fractions.
+    pub fn input(self) -> (String, Unit) {
+        let unit = Unit::ALL
+            .iter()
+            .rev()
+            .copied()
+            .find(|unit| self.0 % unit.plancks() == 0)
+            .unwrap_or(Unit::Pico);
+        ((self.0 / unit.plancks()).to_string(), unit)
+    }
+
+    /// Parses a balance the way the UI displays it, e.g. `1,000.5000 Unit`,
+    /// `1.5000 kUnit` or `500` (in `Unit`). The names of the units, e.g.
+    /// `10 pico`, are accepted as well.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        let (amount, suffix) = match text.find(|c: char| c.is_whitespace()) {
+            Some(space) => (&text[..space], text[space..].trim()),
+            None => (text, ""),
+        };
+        let unit = if suffix.is_empty() {
+            Unit::Unit
+        } else if let Some(unit) = Unit::from_name(suffix) {
+            unit
+        } else {
+            // the SI prefix followed by the name of the token, e.g. `kUnit`
+            let mut chars = suffix.chars();
+            match (chars.next().and_then(Unit::from_prefix), chars.next()) {
+                (Some(unit), Some(next)) if next.is_uppercase() => unit,
+                _ => return Err(format!("unknown unit {:?}", suffix)),
+            }
+        };
+
+        let amount = amount.replace(',', "");
+        let (whole, fraction) = match amount.find('.') {
+            Some(dot) => (&amount[..dot], &amount[dot + 1..]),
+            None => (&amount[..], ""),
+        };
+        let invalid = || format!("{:?} is no valid balance", text);
+        if whole.is_empty()
+            || !whole
+                .chars()
+                .chain(fraction.chars())
+                .all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid())
+        }
+        let whole = whole.parse::<u128>().map_err(|_| invalid())?;
+        let whole = whole.checked_mul(unit.plancks()).ok_or_else(invalid)?;
+
+        // the digits of the fraction beyond the precision of the unit must be zero
+        let precision = unit.plancks().to_string().len() - 1;
+        let (fraction, rest) = fraction.split_at(fraction.len().min(precision));
+        if rest.chars().any(|c| c != '0') {
+            return Err(format!("{:?} is more precise than a planck", text))
+        }
+        let fraction = if fraction.is_empty() {
+            0
+        } else {
+            let digits = fraction.parse::<u128>().map_err(|_| invalid())?;
+            digits * 10u128.pow((precision - fraction.len()) as u32)
+        };
+        whole.checked_add(fraction).map(Self).ok_or_else(invalid)
+    }
+}
+
+/// Formats the balance in `Unit`, with all decimals which are not zero.
+impl fmt::Display for Balance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unit = Unit::Unit.plancks();
+        let fraction = self.0 % unit;
+        if fraction == 0 {
+            write!(f, "{} Unit", self.0 / unit)
+        } else {
+            let fraction = format!("{:0width$}", fraction, width = DECIMALS as usize);
+            write!(
+                f,
+                "{}.{} Unit",
+                self.0 / unit,
+                fraction.trim_end_matches('0')
+            )
+        }
+    }
+}
+
+/// The balance of an account, in plancks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AccountBalance {
+    /// The balance which can be transferred or used to pay fees.
+    pub free: u128,
+    /// The balance which is reserved, e.g. as deposit for stored code.
+    pub reserved: u128,
+}
+
+impl AccountBalance {
+    /// Returns the sum of the free and the reserved balance.
+    pub fn total(&self) -> u128 {
+        self.free.saturating_add(self.reserved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_accepts_the_formats_of_the_ui() {
+        assert_eq!(
+            Balance::parse("1,000.5000 Unit"),
+            Ok(Balance::new(1_000_500, Unit::Milli))
+        );
+        assert_eq!(
+            Balance::parse("1.5000 kUnit"),
+            Ok(Balance::new(1500, Unit::Unit))
+        );
+        assert_eq!(Balance::parse(" 500 "), Ok(Balance::units(500)));
+        assert_eq!(Balance::parse("10 pico"), Ok(Balance::from_plancks(10)));
+        assert_eq!(Balance::parse("2 Kilo"), Ok(Balance::new(2, Unit::Kilo)));
+        assert_eq!(
+            Balance::parse("0.000001 µUnit"),
+            Ok(Balance::from_plancks(1))
+        );
+    }
+
+    #[test]
+    fn parse_rejects_invalid_balances() {
+        assert_eq!(
+            Balance::parse("1 xUnit"),
+            Err("unknown unit \"xUnit\"".to_string())
+        );
+        assert_eq!(Balance::parse("1 k"), Err("unknown unit \"k\"".to_string()));
+        assert_eq!(
+            Balance::parse("0.0000001 µUnit"),
+            Err("\"0.0000001 µUnit\" is more precise than a planck".to_string())
+        );
+        assert!(Balance::parse("").is_err());
+        assert!(Balance::parse(".5 Unit").is_err());
+        assert!(Balance::parse("-1 Unit").is_err());
+        assert!(Balance::parse("1e3 Unit").is_err());
+        assert!(Balance::parse("1000000000000000 TUnit").is_err());
+    }
+
+    #[test]
+    fn input_uses_the_largest_whole_unit() {
+        assert_eq!(
+            Balance::new(100, Unit::Kilo).input(),
+            ("100".to_string(), Unit::Kilo)
+        );
+        assert_eq!(
+            Balance::new(1500, Unit::Unit).input(),
+            ("1500".to_string(), Unit::Unit)
+        );
+        assert_eq!(
+            Balance::from_plancks(1).input(),
+            ("1".to_string(), Unit::Pico)
+        );
+        assert_eq!(Balance::default().input(), ("0".to_string(), Unit::Tril));
+    }
+
+    #[test]
+    fn display_formats_in_units() {
+        assert_eq!(Balance::units(3).to_string(), "3 Unit");
+        assert_eq!(Balance::new(1500, Unit::Milli).to_string(), "1.5 Unit");
+        assert_eq!(Balance::from_plancks(1).to_string(), "0.000000000001 Unit");
+    }
+}
@@ -0,0 +1,306 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A content-addressed cache for the artifacts of contract builds.
+//!
+//! The key of a build is the hash of the contract's sources (including its
+//! `Cargo.lock` and the sources of its path dependencies) and the toolchain.
+//! The artifacts are stored in a directory per key, so they are reused within
+//! a test run and across test runs.
+//!
+//! The `Cargo.lock` is generated before the sources are hashed, otherwise the
+//! one which the first build creates would change the key. Like the build
+//! itself, this happens under the lock of the contract.
+
+use super::cargo_contract::BuildArtifacts;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use sha2::{
+    Digest,
+    Sha256,
+};
+use std::{
+    collections::{
+        HashMap,
+        HashSet,
+    },
+    fs,
+    path::{
+        Path,
+        PathBuf,
+    },
+    process::Command,
+    sync::{
+        Arc,
+        Mutex,
+    },
+};
+
+/// The file in a cache entry which holds the serialized `BuildArtifacts`.
+const ARTIFACTS_FILE: &str = "artifacts.json";
+
+/// The locks of the contracts, by the canonical path of their manifest.
+///
+/// They ensure that a contract is only built once, even if tests are executed in
+/// parallel, while unrelated contracts are still built in parallel. The builds run
+/// on blocking threads (see `cargo_contract::build`), so waiting for a lock
+/// doesn't block the runtime.
+static BUILD_LOCKS: Lazy<Mutex<HashMap<PathBuf, Arc<Mutex<()>>>>> =
+    Lazy::new(Default::default);
+
+/// Returns the artifacts for the contract at `manifest_path`.
+///
+/// If the cache contains artifacts for the same sources and toolchain they are
+/// returned, otherwise the contract is built with `build` and its artifacts are
+/// added to the cache.
+pub(crate) fn cached<F>(manifest_path: &Path, build: F) -> Result<BuildArtifacts, String>
+where
+    F: FnOnce(&Path) -> Result<BuildArtifacts, String>,
+{
+    if !enabled() {
+        return build(manifest_path)
+    }
+    let toolchain = match toolchain() {
+        Ok(toolchain) => toolchain,
+        Err(err) => {
+            log::warn!("Not caching the build of {:?}: {}", manifest_path, err);
+            return build(manifest_path)
+        }
+    };
+
+    let manifest_path = manifest_path
+        .canonicalize()
+        .map_err(|err| format!("unable to resolve {:?}: {:?}", manifest_path, err))?;
+    let lock = BUILD_LOCKS
+        .lock()
+        .expect("the build locks are poisoned")
+        .entry(manifest_path.clone())
+        .or_default()
+        .clone();
+    let (_guard, poisoned) = match lock.lock() {
+        Ok(guard) => (guard, false),
+        Err(err) => (err.into_inner(), true),
+    };
+
+    let entry = cache_dir().join(key(&manifest_path, &toolchain)?);
+    if poisoned {
+        // a build panicked while it held the lock, so the entry may be half-written.
+        // the next build of the contract gets a new lock.
+        log::warn!("Removing the cache entry {:?} of a panicked build", entry);
+        if entry.exists() {
+            fs::remove_dir_all(&entry)
+                .map_err(|err| format!("unable to remove {:?}: {:?}", entry, err))?;
+        }
+        let mut locks = BUILD_LOCKS.lock().expect("the build locks are poisoned");
+        if locks
+            .get(&manifest_path)
+            .map_or(false, |current| Arc::ptr_eq(current, &lock))
+        {
+            locks.remove(&manifest_path);
+        }
+    }
+    if let Some(artifacts) = lookup(&entry) {
+        log::info!(
+            "Using the cached build of {:?} from {:?}",
+            manifest_path,
+            entry
+        );
+        return Ok(artifacts)
+    }
+
+    let artifacts = build(&manifest_path)?;
+    store(&entry, artifacts)
+}
+
+/// Returns `false` if the cache is disabled via `WATERFALL_BUILD_CACHE=false`.
+fn enabled() -> bool {
+    std::env::var("WATERFALL_BUILD_CACHE")
+        .map(|cache| cache != "false")
+        .unwrap_or(true)
+}
+
+/// Returns the directory which contains the cache entries.
+///
+/// Defaults to `waterfall-build-cache` in the temporary directory, can be
+/// overridden with the environment variable `WATERFALL_BUILD_CACHE_DIR`.
+fn cache_dir() -> PathBuf {
+    std::env::var("WATERFALL_BUILD_CACHE_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| std::env::temp_dir().join("waterfall-build-cache"))
+}
+
+/// Returns the cache key for the contract at `manifest_path`, which is built with
+/// the `toolchain`.
+fn key(manifest_path: &Path, toolchain: &[u8]) -> Result<String, String> {
+    let mut hasher = Sha256::new();
+    hasher.update(toolchain);
+    let dir = manifest_path
+        .parent()
+        .ok_or_else(|| format!("{:?} has no parent directory", manifest_path))?;
+    generate_lockfile(manifest_path, dir)?;
+    hash_crate(dir, &mut hasher, &mut HashSet::new())
+        .map_err(|err| format!("unable to hash the sources in {:?}: {:?}", dir, err))?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Generates the `Cargo.lock` of the crate in `dir`, unless it already exists.
+fn generate_lockfile(manifest_path: &Path, dir: &Path) -> Result<(), String> {
+    if dir.join("Cargo.lock").is_file() {
+        return Ok(())
+    }
+    log::info!("Generating the Cargo.lock of {:?}", manifest_path);
+    let status = Command::new("cargo")
+        .args(&["+nightly", "generate-lockfile", "--manifest-path"])
+        .arg(manifest_path)
+        .status()
+        .map_err(|err| format!("unable to run `cargo generate-lockfile`: {:?}", err))?;
+    if !status.success() {
+        return Err(format!(
+            "`cargo generate-lockfile` failed for {:?} with {}",
+            manifest_path, status
+        ))
+    }
+    Ok(())
+}
+
+/// Returns the versions of the toolchain which is used to build the contracts.
+fn toolchain() -> Result<Vec<u8>, String> {
+    let commands: [(&str, &[&str]); 3] = [
+        ("rustc", &["+nightly", "-vV"]),
+        ("cargo", &["+nightly", "contract", "--version"]),
+        ("wasm-opt", &["--version"]),
+    ];
+    let mut versions = Vec::new();
+    for (program, args) in commands.iter() {
+        let command = format!("{} {}", program, args.join(" "));
+        let output = Command::new(program)
+            .args(*args)
+            .output()
+            .map_err(|err| format!("unable to run `{}`: {:?}", command, err))?;
+        if !output.status.success() {
+            return Err(format!("`{}` failed with {}", command, output.status))
+        }
+        versions.extend(output.stdout);
+    }
+    Ok(versions)
+}
+
+/// Hashes all files of the crate in `dir` and of its path dependencies.
+///
+/// Only the `Cargo.lock` of the crate which is built is hashed, the ones of its
+/// path dependencies are created once these are built themselves.
+fn hash_crate(
+    dir: &Path,
+    hasher: &mut Sha256,
+    visited: &mut HashSet<PathBuf>,
+) -> std::io::Result<()> {
+    let dir = dir.canonicalize()?;
+    let lockfile = visited.is_empty();
+    if !visited.insert(dir.clone()) {
+        return Ok(())
+    }
+    hash_dir(&dir, &dir, lockfile, hasher)?;
+
+    let manifest = fs::read_to_string(dir.join("Cargo.toml"))?;
+    let re_path = Regex::new(r#"path\s*=\s*"([^"]+)""#).expect("invalid regex");
+    for captures in re_path.captures_iter(&manifest) {
+        let dependency = dir.join(&captures[1]);
+        // the `path` of a `[lib]` section points to a file, not to a crate
+        if dependency.join("Cargo.toml").is_file() {
+            hash_crate(&dependency, hasher, visited)?;
+        }
+    }
+    Ok(())
+}
+
+/// Hashes the paths (relative to `root`) and contents of all files in `dir`.
+///
+/// Build outputs in `target` directories and hidden files are skipped, as well as
+/// `Cargo.lock` files, except the one directly in `dir` if `lockfile` is set.
+fn hash_dir(
+    root: &Path,
+    dir: &Path,
+    lockfile: bool,
+    hasher: &mut Sha256,
+) -> std::io::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let name = entry.file_name();
+        if name == "target"
+            || name.to_string_lossy().starts_with('.')
+            || (name == "Cargo.lock" && !lockfile)
+        {
+            continue
+        }
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            // nested crates, e.g. the sub-contracts of the `delegator`, get their
+            // `Cargo.lock` once they are built themselves
+            hash_dir(root, &path, false, hasher)?;
+        } else {
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            hasher.update(relative.to_string_lossy().as_bytes());
+            hasher.update(fs::read(&path)?);
+        }
+    }
+    Ok(())
+}
+
+/// Returns the artifacts in the cache `entry`, if it exists and is complete.
+fn lookup(entry: &Path) -> Option<BuildArtifacts> {
+    let artifacts = fs::read(entry.join(ARTIFACTS_FILE)).ok()?;
+    let artifacts: BuildArtifacts = serde_json::from_slice(&artifacts).ok()?;
+    let complete = [
+        &artifacts.contract_bundle,
+        &artifacts.wasm,
+        &artifacts.metadata,
+    ]
+    .iter()
+    .all(|path| path.is_file());
+    if complete {
+        Some(artifacts)
+    } else {
+        None
+    }
+}
+
+/// Copies the `artifacts` into the cache `entry` and returns the artifacts with
+/// their paths in the cache.
+fn store(entry: &Path, artifacts: BuildArtifacts) -> Result<BuildArtifacts, String> {
+    let copy = |path: &Path| -> Result<PathBuf, String> {
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| format!("{:?} is no file", path))?;
+        let cached = entry.join(file_name);
+        fs::copy(path, &cached).map_err(|err| {
+            format!("unable to copy {:?} to the cache: {:?}", path, err)
+        })?;
+        Ok(cached)
+    };
+    fs::create_dir_all(entry)
+        .map_err(|err| format!("unable to create {:?}: {:?}", entry, err))?;
+    let cached = BuildArtifacts {
+        contract_bundle: copy(&artifacts.contract_bundle)?,
+        wasm: copy(&artifacts.wasm)?,
+        metadata: copy(&artifacts.metadata)?,
+        ..artifacts
+    };
+    let json = serde_json::to_vec_pretty(&cached)
+        .map_err(|err| format!("unable to serialize {:?}: {:?}", cached, err))?;
+    fs::write(entry.join(ARTIFACTS_FILE), json)
+        .map_err(|err| format!("unable to write to {:?}: {:?}", entry, err))?;
+    log::info!("Added the build artifacts to the cache at {:?}", entry);
+    Ok(cached)
+}
@@ -0,0 +1,422 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use once_cell::sync::Lazy;
+use std::{
+    fs::{
+        self,
+        File,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+    process,
+    sync::{
+        atomic::{
+            AtomicUsize,
+            Ordering,
+        },
+        Once,
+    },
+    time::{
+        Duration,
+        Instant,
+    },
+};
+use tokio::{
+    io::{
+        AsyncReadExt,
+        AsyncWriteExt,
+    },
+    net::TcpStream,
+    sync::Mutex,
+};
+
+/// How long we wait for a spawned node to answer RPC requests.
+const READY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The interval in which we check if a spawned node is ready.
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long we wait for a node to exit after it was asked to stop.
+const STOP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The chain state which is used if neither the test nor `WATERFALL_CHAIN` specify one.
+const DEFAULT_CHAIN: &str = "fresh";
+
+/// The chain database of a node at genesis, which is copied for each test using the
+/// `snapshot` chain state.
+///
+/// It is created once per test run, by the first test which needs it.
+static GENESIS_SNAPSHOT: Lazy<Mutex<Option<PathBuf>>> = Lazy::new(Default::default);
+
+/// Used to create unique base paths for the restored snapshots.
+static RESTORED_SNAPSHOTS: AtomicUsize = AtomicUsize::new(0);
+
+/// Ensures that the removal of the genesis snapshot at exit is only registered
+/// once.
+static REMOVE_GENESIS_SNAPSHOT_AT_EXIT: Once = Once::new();
+
+/// A `canvas-node` which the tests are executed against.
+///
+/// Unless `CANVAS_NODE_URL` is set, a fresh node is spawned on free ports for it.
+/// A spawned node is killed when the `CanvasNode` is dropped.
+pub struct CanvasNode {
+    /// The spawned node process, `None` if an already running node is used.
+    process: Option<process::Child>,
+    /// The WebSocket URL under which the node can be reached.
+    ws_url: String,
+    /// The file which the output of a spawned node is written to.
+    log_path: Option<PathBuf>,
+    /// The copy of the genesis snapshot which the node uses, it is removed
+    /// together with the node.
+    restored_snapshot: Option<PathBuf>,
+}
+
+impl CanvasNode {
+    /// Returns the node the tests should use.
+    ///
+    /// If the environment variable `CANVAS_NODE_URL` is set the node running at
+    /// this URL is used, otherwise a new node is spawned. The `chain` determines
+    /// which state the spawned node starts with:
+    ///
+    /// * `fresh` ‒ the node starts with a new chain from genesis.
+    /// * `snapshot` ‒ the node starts with a copy of a chain database which was
+    ///   snapshotted at genesis. The snapshot is only created once per test run.
+    ///
+    /// In both cases the test starts with the known balances of the dev accounts.
+    pub async fn start(chain: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        if let Ok(ws_url) = std::env::var("CANVAS_NODE_URL") {
+            log::warn!(
+                "using the already running canvas-node at {:?}, the chain state \
+                is shared with other tests",
+                ws_url
+            );
+            return Ok(Self {
+                process: None,
+                ws_url,
+                log_path: None,
+                restored_snapshot: None,
+            })
+        }
+        match chain {
+            "fresh" => Self::spawn(None).await,
+            "snapshot" => {
+                let base_path = restore_genesis_snapshot().await?;
+                let mut node = Self::spawn(Some(&base_path)).await;
+                match node.as_mut() {
+                    Ok(node) => node.restored_snapshot = Some(base_path),
+                    Err(_) => {
+                        let _ = fs::remove_dir_all(&base_path);
+                    }
+                }
+                node
+            }
+            _ => {
+                Err(format!(
+                    "unknown chain state {:?}, expected `fresh` or `snapshot`",
+                    chain
+                )
+                .into())
+            }
+        }
+    }
+
+    /// Spawns `canvas --dev` on free ports and waits until it answers RPC
+    /// requests.
+    ///
+    /// The chain database is stored at `base_path`, if none is given it is stored
+    /// in a temporary directory (`--tmp`).
+    ///
+    /// The binary can be changed with the environment variable `CANVAS_NODE_BINARY`,
+    /// e.g. to `canvas-rand-extension`.
+    pub async fn spawn(
+        base_path: Option<&Path>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let binary = node_binary();
+        let p2p_port = pick_port().to_string();
+        let rpc_port = pick_port();
+        let ws_port = pick_port();
+        let log_path =
+            std::env::temp_dir().join(format!("waterfall-canvas-node-{}.log", ws_port));
+        log::info!(
+            "spawning {:?} with rpc port {:?} and ws port {:?}, logging to {:?}",
+            binary,
+            rpc_port,
+            ws_port,
+            log_path
+        );
+
+        let log = File::create(&log_path)?;
+        let mut command = process::Command::new(&binary);
+        match base_path {
+            Some(base_path) => command.arg("--base-path").arg(base_path),
+            None => command.arg("--tmp"),
+        };
+        let process = command
+            .args(&[
+                "--dev",
+                // otherwise nodes of parallel tests discover each other and share
+                // their chain
+                "--no-mdns",
+                "--no-prometheus",
+                "--port",
+                &p2p_port,
+                "--rpc-port",
+                &rpc_port.to_string(),
+                "--ws-port",
+                &ws_port.to_string(),
+            ])
+            .stdout(log.try_clone()?)
+            .stderr(log)
+            .spawn()
+            .map_err(|err| format!("{:?} can not be spawned: {:?}", binary, err))?;
+
+        let mut node = Self {
+            process: Some(process),
+            ws_url: format!("ws://127.0.0.1:{}", ws_port),
+            log_path: Some(log_path),
+            restored_snapshot: None,
+        };
+        node.wait_until_ready(rpc_port).await?;
+        Ok(node)
+    }
+
+    /// Returns the WebSocket URL of the node, e.g. `ws://127.0.0.1:9944`.
+    pub fn ws_url(&self) -> &str {
+        &self.ws_url
+    }
+
+    /// Returns the path to the log of the node, if it was spawned by us.
+    pub fn log_path(&self) -> Option<&Path> {
+        self.log_path.as_deref()
+    }
+
+    /// Stops a spawned node gracefully with `SIGTERM` and waits until it exited,
+    /// so that its chain database is left in a consistent state.
+    ///
+    /// If the node doesn't exit within `STOP_TIMEOUT` an error is returned, the
+    /// node is then killed when it is dropped.
+    pub async fn stop(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let process = match self.process.as_mut() {
+            Some(process) => process,
+            None => return Ok(()),
+        };
+        log::info!("stopping the canvas-node at {:?}", self.ws_url);
+        // SAFETY: `kill` has no memory safety preconditions, the pid belongs to
+        // our child process which wasn't reaped yet
+        if unsafe { libc::kill(process.id() as libc::pid_t, libc::SIGTERM) } != 0 {
+            return Err(format!(
+                "unable to stop the canvas-node: {}",
+                std::io::Error::last_os_error()
+            )
+            .into())
+        }
+        let started = Instant::now();
+        loop {
+            if let Some(status) = process.try_wait()? {
+                log::info!("the canvas-node exited with {}", status);
+                self.process = None;
+                return Ok(())
+            }
+            if started.elapsed() > STOP_TIMEOUT {
+                return Err(format!(
+                    "the canvas-node didn't exit within {:?}, see {:?}",
+                    STOP_TIMEOUT, self.log_path
+                )
+                .into())
+            }
+            tokio::time::sleep(READY_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Polls the `system_health` RPC of the node until it answers.
+    async fn wait_until_ready(
+        &mut self,
+        rpc_port: u16,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let started = Instant::now();
+        loop {
+            if let Some(status) = self
+                .process
+                .as_mut()
+                .map(process::Child::try_wait)
+                .transpose()?
+                .flatten()
+            {
+                return Err(format!(
+                    "the canvas-node exited with {} before it was ready, see {:?}",
+                    status, self.log_path
+                )
+                .into())
+            }
+            if is_ready(rpc_port).await {
+                log::info!("canvas-node is ready after {:?}", started.elapsed());
+                return Ok(())
+            }
+            if started.elapsed() > READY_TIMEOUT {
+                return Err(format!(
+                    "the canvas-node was not ready after {:?}, see {:?}",
+                    READY_TIMEOUT, self.log_path
+                )
+                .into())
+            }
+            tokio::time::sleep(READY_POLL_INTERVAL).await;
+        }
+    }
+}
+
+impl Drop for CanvasNode {
+    fn drop(&mut self) {
+        if let Some(process) = self.process.as_mut() {
+            log::info!(
+                "killing the canvas-node at {:?}, its log is at {:?}",
+                self.ws_url,
+                self.log_path
+            );
+            // the node might have exited already, e.g. if it failed to start. A
+            // panic here would abort the test and hide the actual error.
+            if let Err(err) = process.kill() {
+                log::warn!("unable to kill the canvas-node: {:?}", err);
+            }
+            // reap the process, so that no zombie is left behind
+            let _ = process.wait();
+        }
+        if let Some(restored_snapshot) = self.restored_snapshot.take() {
+            if let Err(err) = fs::remove_dir_all(&restored_snapshot) {
+                log::warn!("unable to remove {:?}: {:?}", restored_snapshot, err);
+            }
+        }
+    }
+}
+
+/// Returns the chain state the tests should start with.
+///
+/// Defaults to `fresh`, can be overridden with the environment variable
+/// `WATERFALL_CHAIN`.
+pub fn chain_from_env() -> String {
+    std::env::var("WATERFALL_CHAIN").unwrap_or_else(|_| String::from(DEFAULT_CHAIN))
+}
+
+/// Copies the genesis snapshot to a new directory and returns its path.
+///
+/// The snapshot is created if this hasn't happened yet in this test run.
+async fn restore_genesis_snapshot() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let mut snapshot = GENESIS_SNAPSHOT.lock().await;
+    if snapshot.is_none() {
+        *snapshot = Some(create_genesis_snapshot().await?);
+    }
+    let snapshot = snapshot.as_ref().expect("snapshot was just created");
+
+    let restored = std::env::temp_dir().join(format!(
+        "waterfall-canvas-node-{}-{}",
+        process::id(),
+        RESTORED_SNAPSHOTS.fetch_add(1, Ordering::SeqCst)
+    ));
+    log::info!("restoring the genesis snapshot to {:?}", restored);
+    copy_dir(snapshot, &restored)?;
+    Ok(restored)
+}
+
+/// Spawns a node which stores its chain database in a new directory and stops
+/// it as soon as it is ready. No extrinsics were submitted to this node, so the
+/// database contains the genesis state of all accounts.
+///
+/// The snapshot is removed when the test run exits.
+async fn create_genesis_snapshot() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let snapshot = genesis_snapshot_path();
+    if snapshot.exists() {
+        fs::remove_dir_all(&snapshot)?;
+    }
+    REMOVE_GENESIS_SNAPSHOT_AT_EXIT.call_once(|| {
+        // SAFETY: the callback doesn't unwind, errors are ignored
+        if unsafe { libc::atexit(remove_genesis_snapshot) } != 0 {
+            log::warn!("unable to remove the genesis snapshot at exit");
+        }
+    });
+    log::info!("creating the genesis snapshot at {:?}", snapshot);
+    let mut node = CanvasNode::spawn(Some(&snapshot)).await?;
+    if let Err(err) = node.stop().await {
+        // a database which was written to while the node was killed can't be used
+        drop(node);
+        let _ = fs::remove_dir_all(&snapshot);
+        return Err(err)
+    }
+    Ok(snapshot)
+}
+
+/// Returns the directory of the genesis snapshot of this test run.
+fn genesis_snapshot_path() -> PathBuf {
+    std::env::temp_dir().join(format!("waterfall-canvas-node-{}-genesis", process::id()))
+}
+
+/// Removes the genesis snapshot, this is registered to run at the exit of the
+/// test run.
+extern "C" fn remove_genesis_snapshot() {
+    let _ = fs::remove_dir_all(genesis_snapshot_path());
+}
+
+/// Recursively copies the directory `from` to `to`.
+fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns `true` if the node with the HTTP RPC server at `rpc_port` answers a
+/// `system_health` request.
+async fn is_ready(rpc_port: u16) -> bool {
+    let body = r#"{"jsonrpc":"2.0","id":1,"method":"system_health","params":[]}"#;
+    let request = format!(
+        "POST / HTTP/1.1\r\n\
+        Host: 127.0.0.1:{}\r\n\
+        Content-Type: application/json\r\n\
+        Content-Length: {}\r\n\
+        Connection: close\r\n\r\n{}",
+        rpc_port,
+        body.len(),
+        body
+    );
+    let mut stream = match TcpStream::connect(("127.0.0.1", rpc_port)).await {
+        Ok(stream) => stream,
+        Err(_) => return false,
+    };
+    if stream.write_all(request.as_bytes()).await.is_err() {
+        return false
+    }
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.is_ok() && response.contains("\"result\"")
+}
+
+/// Returns the node binary which should be spawned.
+///
+/// Defaults to `canvas`, can be overridden with the environment variable
+/// `CANVAS_NODE_BINARY`.
+fn node_binary() -> String {
+    std::env::var("CANVAS_NODE_BINARY").unwrap_or_else(|_| String::from("canvas"))
+}
+
+/// Returns a free port.
+fn pick_port() -> u16 {
+    portpicker::pick_unused_port().expect("no free port")
+}
@@ -12,45 +12,112 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use super::{
+    account::{
+        Account,
+        DEFAULT_PASSWORD,
+    },
+    backend::{
+        Call,
+        ContractsBackend,
+        Error,
+        Event,
+        Events,
+        RpcOutcome,
+        Upload,
+    },
+    balance::{
+        AccountBalance,
+        Balance,
+    },
+    chain::Chain,
+    contract_bundle::ContractBundle,
+    node_rpc,
+    pages::{
+        AccountsPage,
+        ExecutePage,
+        InstantiatePage,
+        NotificationsPanel,
+        UploadPage,
+    },
+    selectors::{
+        self,
+        Selectors,
+    },
+    ui_server,
+    validation::{
+        self,
+        CallKind,
+    },
+    wait::{
+        Wait,
+        WaitError,
+    },
+};
+use async_trait::async_trait;
 use fantoccini::{
-    error::CmdError,
     Client,
     ClientBuilder,
-    Locator,
 };
-use psutil::process::processes;
-use regex::Regex;
+use once_cell::sync::Lazy;
 use serde_json::{
     self,
     map::Map,
     value::Value,
 };
 use std::{
-    path::PathBuf,
+    collections::HashMap,
+    fs::{
+        self,
+        File,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
     process,
+    sync::Arc,
+    time::Duration,
 };
+use tokio::sync::Mutex;
+
+/// The release of the UI as detected by the first `CanvasUi` which needed it,
+/// `Some(None)` if the UI doesn't display it.
+static DETECTED_VERSION: Lazy<Mutex<Option<Option<String>>>> =
+    Lazy::new(Default::default);
 
 /// Holds everything necessary to interact with the `canvas-ui`.
 pub struct CanvasUi {
     client: Client,
     geckodriver: process::Child,
+    /// The file which the output of `geckodriver` is written to.
+    geckodriver_log: PathBuf,
+    /// The WebSocket URL of the node which the UI is connected to.
+    node_url: String,
+    /// The connection to the same node, to read what the UI doesn't display.
+    chain: Chain,
+    /// The bundles of the contracts uploaded with this instance, by address.
+    contracts: HashMap<String, ContractBundle>,
+    /// The selectors for the release of the UI, loaded on first use.
+    selectors: Option<Arc<Selectors>>,
 }
 
 impl CanvasUi {
     /// Creates a new `CanvasUi` instance.
     ///
     /// As part of this set-up a `geckodriver` instance is spawned to a free port.
-    pub async fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        assert_canvas_node_running();
-
-        // the output is unfortunately always printed
-        // https://users.rust-lang.org/t/cargo-test-printing-println-output-from-child-threads/11627
-        // https://github.com/rust-lang/rust/issues/35136
+    /// The UI is connected to the node at `node_url`.
+    pub async fn new(node_url: &str) -> Result<Self, Box<dyn std::error::Error>> {
         let port = format!("{}", portpicker::pick_unused_port().expect("no free port"));
         log::info!("Picked free port {:?} for geckodriver instance", port);
+        // the output is written to a file, it becomes part of the failure artifacts
+        let geckodriver_log =
+            std::env::temp_dir().join(format!("waterfall-geckodriver-{}.log", port));
+        let log = File::create(&geckodriver_log)?;
         let geckodriver = process::Command::new("geckodriver")
-            .args(&["--port", &port, "--log", "fatal"])
-            .stdout(std::process::Stdio::piped())
+            .args(&["--port", &port, "--log", "info"])
+            .stdout(log.try_clone()?)
+            .stderr(log)
             .spawn()
             .expect("geckodriver can not be spawned");
 
@@ -62,14 +129,199 @@ impl CanvasUi {
         Ok(Self {
             client,
             geckodriver,
+            geckodriver_log,
+            node_url: node_url.to_string(),
+            chain: Chain::connect(node_url).await?,
+            contracts: HashMap::new(),
+            selectors: None,
         })
     }
 
+    /// Waits for the outcome of the extrinsic which was submitted after the block
+    /// `since` and returns its events.
+    ///
+    /// Fails with the dispatch error if the extrinsic failed.
+    async fn extrinsic_events(
+        &mut self,
+        notifications: &mut NotificationsPanel,
+        since: u32,
+    ) -> Result<Events, Error> {
+        notifications.wait_for_extrinsic_result().await?;
+        let displayed = notifications.events().await?;
+        notifications.dismiss_all().await?;
+
+        // the UI only displays the names of the events, their fields are read from
+        // the chain
+        let names: Vec<String> = displayed.iter().map(Event::name).collect();
+        let events = match self
+            .chain
+            .find_extrinsic_events(since, &names)
+            .await
+            .map_err(Error::Other)?
+        {
+            Some(mut events) => {
+                for event in events.iter_mut() {
+                    if let Some(shown) =
+                        displayed.iter().find(|shown| shown.name() == event.name())
+                    {
+                        event.status = shown.status.clone();
+                    }
+                }
+                let mut events = Events::new(events);
+                events.decode_contract_events(&self.contracts);
+                events
+            }
+            None => {
+                log::warn!("unable to find the events {:?} on the chain", names);
+                Events::new(displayed)
+            }
+        };
+
+        let success = events.contains("system.ExtrinsicSuccess");
+        let failure = events.contains("system.ExtrinsicFailed");
+        match (success, failure) {
+            (true, false) => Ok(events),
+            (false, true) => {
+                let dispatch_error = self.chain.dispatch_error(&events).await;
+                Err(Error::extrinsic_failed(dispatch_error, events))
+            }
+            (false, false) | (true, true) => Err(Error::AmbiguousOutcome(events)),
+        }
+    }
+
+    /// Fills in the instantiation form `instantiate_page` as described by
+    /// `upload_input` and returns the address of the instantiated contract, which
+    /// is described by `bundle`.
+    async fn instantiate(
+        &mut self,
+        mut instantiate_page: InstantiatePage,
+        upload_input: Upload,
+        bundle: ContractBundle,
+    ) -> Result<String, Error> {
+        let selectors = self.selectors().await.map_err(Error::Other)?;
+        instantiate_page.open_constructor_details().await?;
+        if let Some(caller) = &upload_input.caller {
+            instantiate_page.select_caller(caller.name()).await?;
+        }
+        for (key, value) in upload_input.initial_values.iter() {
+            instantiate_page.set_argument(key, value).await?;
+        }
+        if let Some(constructor) = upload_input.constructor {
+            instantiate_page.select_constructor(&constructor).await?;
+        }
+        let (endowment, unit) = upload_input.endowment.input();
+        instantiate_page
+            .set_endowment(&endowment, unit.name())
+            .await?;
+        instantiate_page.toggle_unique_salt().await?;
+        let password = upload_input.caller.as_ref().and_then(Account::password);
+        instantiate_page.instantiate(password).await?;
+
+        let mut notifications = NotificationsPanel::new(&self.client, &selectors);
+        notifications.dismiss_all().await?;
+        // wait for disappearance animation to finish instead
+        // otherwise the notifications might occlude buttons
+        notifications.hide().await?;
+
+        let addr = instantiate_page.execute_contract().await?;
+        self.contracts.insert(addr.clone(), bundle);
+        Ok(addr)
+    }
+
+    /// Checks `call` against the metadata of the called contract.
+    ///
+    /// Contracts which were not uploaded by this instance (e.g. instantiated by
+    /// another contract) can't be checked.
+    fn check_call(&self, call: &Call, kind: CallKind) -> Result<(), String> {
+        match self.contracts.get(&call.contract_address) {
+            Some(bundle) => validation::check_call(bundle, call, kind),
+            None => {
+                log::info!(
+                    "the metadata of {:?} is unknown, the call can't be checked",
+                    call.contract_address
+                );
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns the selectors for the release of the UI.
+    ///
+    /// The release is taken from `CANVAS_UI_VERSION` or detected from the UI when
+    /// this method is called for the first time.
+    async fn selectors(&mut self) -> Result<Arc<Selectors>, Box<dyn std::error::Error>> {
+        if let Some(selectors) = &self.selectors {
+            return Ok(selectors.clone())
+        }
+        let version = match selectors::version_from_env() {
+            Some(version) => Some(version),
+            None => self.detect_version().await?,
+        };
+        let selectors = Arc::new(selectors::load(version.as_deref())?);
+        self.selectors = Some(selectors.clone());
+        Ok(selectors)
+    }
+
+    /// Returns the release of the UI as it is displayed in the UI, or `None` if
+    /// it is not displayed.
+    ///
+    /// The release is only detected once per test run, all instances use the
+    /// same UI.
+    async fn detect_version(&mut self) -> Result<Option<String>, WaitError> {
+        let mut detected_version = DETECTED_VERSION.lock().await;
+        if let Some(version) = detected_version.as_ref() {
+            return Ok(version.clone())
+        }
+
+        self.client.goto(&self.page("/")).await?;
+        // the script only returns `null` until the UI is rendered, if there is
+        // no version marker afterwards we don't wait for it
+        let displayed = Wait::new("the UI to render")
+            .timeout(Duration::from_secs(10))
+            .until(|| {
+                let mut client = self.client.clone();
+                async move {
+                    let version = client
+                        .execute(selectors::DETECT_VERSION_SCRIPT, Vec::new())
+                        .await?;
+                    Ok(version.as_str().map(String::from))
+                }
+            })
+            .await;
+        let version = match displayed {
+            Ok(version) if !version.is_empty() => {
+                log::info!("detected the UI version {}", version);
+                Some(version)
+            }
+            Ok(_) | Err(WaitError::Timeout { .. }) => {
+                log::warn!("unable to detect the UI version, set `CANVAS_UI_VERSION`");
+                None
+            }
+            Err(err) => return Err(err),
+        };
+        *detected_version = Some(version.clone());
+        Ok(version)
+    }
+
+    /// Returns the URL to the `route` in the Canvas UI, e.g. `/upload`.
+    ///
+    /// The node which the UI connects to is passed via the `rpc` query parameter.
+    fn page(&self, route: &str) -> String {
+        url(&format!(
+            "/?rpc={}#{}",
+            encode_uri_component(&self.node_url),
+            route
+        ))
+    }
+}
+
+#[async_trait(?Send)]
+impl ContractsBackend for CanvasUi {
     /// Closes the `client`.
     ///
     /// It would be better to have this in `CanvasUi::Drop`, but this is not possible
     /// due to the async nature of the `client.close()` method.
-    pub async fn shutdown(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    async fn shutdown(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         if !closing_enabled() {
             log::info!(
                 "keeping client open due to env variable `WATERFALL_CLOSE_BROWSER`"
@@ -80,29 +332,102 @@ impl CanvasUi {
         Ok(())
     }
 
-    /// Returns the balance postfix numbers.
-    pub async fn balance_postfix(
+    /// Saves a screenshot, the page source, the current URL, the contents of the
+    /// notification panel and the `geckodriver` log to `dir`.
+    ///
+    /// Each artifact is saved on a best effort basis, if one fails the others are
+    /// still saved.
+    async fn save_failure_artifacts(
         &mut self,
-        account: String,
-    ) -> Result<u128, Box<dyn std::error::Error>> {
-        self.client
-            .goto(
-                "https://polkadot.js.org/apps/?rpc=ws%3A%2F%2F127.0.0.1%3A9944#/accounts",
-            )
-            .await?;
-        std::thread::sleep(std::time::Duration::from_secs(3));
+        dir: &Path,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match self.client.screenshot().await {
+            Ok(png) => fs::write(dir.join("screenshot.png"), png)?,
+            Err(err) => log::error!("unable to take a screenshot: {:?}", err),
+        }
+        match self.client.source().await {
+            Ok(html) => fs::write(dir.join("page.html"), html)?,
+            Err(err) => log::error!("unable to get the page source: {:?}", err),
+        }
+        match self.client.current_url().await {
+            Ok(url) => fs::write(dir.join("url.txt"), url.as_str())?,
+            Err(err) => log::error!("unable to get the current url: {:?}", err),
+        }
+        if let Some(selectors) = &self.selectors {
+            match NotificationsPanel::new(&self.client, selectors)
+                .text()
+                .await
+            {
+                Ok(notifications) => {
+                    fs::write(dir.join("notifications.txt"), notifications)?
+                }
+                Err(err) => log::error!("unable to read the notifications: {:?}", err),
+            }
+        }
+        fs::copy(&self.geckodriver_log, dir.join("geckodriver.log"))?;
+        Ok(())
+    }
 
-        let path = format!(
-            "//div[. = '{}']/ancestor::tr//span[@class = 'ui--FormatBalance-postfix']",
-            account
-        );
-        let txt = self
-            .client
-            .find(Locator::XPath(&path))
-            .await?
-            .text()
-            .await?;
-        Ok(txt.parse::<u128>().expect("failed parsing"))
+    /// Returns the balance of `account` as read from the chain, the UI only
+    /// displays it rounded.
+    async fn balance(&mut self, account: &Account) -> Result<AccountBalance, Error> {
+        self.chain
+            .account_balance(&account.account_id())
+            .await
+            .map_err(Error::Other)
+    }
+
+    /// Adds the account `name` to the keyring of the UI, protected with the
+    /// `DEFAULT_PASSWORD`.
+    async fn create_account(&mut self, name: &str, suri: &str) -> Result<Account, Error> {
+        let account =
+            Account::from_suri(name, suri).map_err(|err| Error::Other(err.into()))?;
+        let selectors = self.selectors().await.map_err(Error::Other)?;
+        let mut accounts_page =
+            AccountsPage::open(&self.client, &selectors, &self.page("/accounts")).await?;
+        accounts_page
+            .add_account(name, suri, DEFAULT_PASSWORD)
+            .await?;
+        Ok(account)
+    }
+
+    /// Restores the account from the keystore into the keyring of the UI.
+    async fn import_account(
+        &mut self,
+        path: &Path,
+        password: &str,
+    ) -> Result<Account, Error> {
+        let account = Account::from_keystore(path, password)
+            .map_err(|err| Error::Other(err.into()))?;
+        let selectors = self.selectors().await.map_err(Error::Other)?;
+        let mut accounts_page =
+            AccountsPage::open(&self.client, &selectors, &self.page("/accounts")).await?;
+        accounts_page
+            .restore_json(account.name(), path, password)
+            .await?;
+        Ok(account)
+    }
+
+    /// Sends `amount` via the transfer modal of `from` on the accounts page.
+    async fn fund_account(
+        &mut self,
+        account: &Account,
+        from: &Account,
+        amount: Balance,
+    ) -> Result<Events, Error> {
+        let selectors = self.selectors().await.map_err(Error::Other)?;
+        let mut accounts_page =
+            AccountsPage::open(&self.client, &selectors, &self.page("/accounts")).await?;
+        let (amount, unit) = amount.input();
+        accounts_page
+            .transfer(from.name(), &account.address(), &amount, unit.name())
+            .await?;
+
+        let mut notifications = NotificationsPanel::new(&self.client, &selectors);
+        notifications.wait_until_queued().await?;
+        let since = self.chain.best_block_number().await.map_err(Error::Other)?;
+        accounts_page.sign_and_submit().await?;
+        self.extrinsic_events(&mut notifications, since).await
     }
 
     /// Uploads the contract behind `contract_path`.
@@ -111,287 +436,69 @@ impl CanvasUi {
     ///
     /// This method must not make any assumptions about the state of the Ui before
     /// the method is invoked. It must e.g. open the upload page right at the start.
-    pub async fn execute_upload(
-        &mut self,
-        upload_input: Upload,
-    ) -> Result<String, Box<dyn std::error::Error>> {
-        log::info!("opening {:?}", url("/#/upload"));
-        self.client.goto(&url("/#/upload")).await?;
-
-        // We wait until the settings are visible to make sure the page is ready
-        log::info!("waiting for settings to become visible");
-        self.client
-            .wait_for_find(Locator::XPath("//*[contains(text(),'Local Node')]"))
-            .await?;
+    async fn execute_upload(&mut self, upload_input: Upload) -> Result<String, Error> {
+        let bundle = ContractBundle::from_path(&upload_input.contract_path)?;
+        validation::check_upload(&bundle, &upload_input)
+            .map_err(Error::UploadRejected)?;
+        let selectors = self.selectors().await.map_err(Error::Other)?;
 
-        // We should get rid of this `sleep`. The problem is that the "Skip Intro" button
-        // sometimes appears after a bit of time and sometimes it doesn't (if it was already
-        // clicked away during the session).
-        std::thread::sleep(std::time::Duration::from_secs(3));
-
-        log::info!("click skip intro button, if it is available");
-        if let Ok(skip_button) = self
-            .client
-            .find(Locator::XPath("//button[contains(text(),'Skip Intro')]"))
-            .await
-        {
-            log::info!("found skip button");
-            skip_button.click().await?;
-        } else {
-            // The "Skip Intro" button is not always there, e.g. if multiple contracts
-            // are deployed subsequently in the same browser session by one test.
-            eprintln!("did not find 'Skip Intro' button, ignoring it.");
-        }
-
-        log::info!("click settings");
-        self.client
-            .find(Locator::Css(".app--SideBar-settings"))
-            .await?
-            .click()
-            .await?;
-
-        log::info!("click local node");
-        self.client
-            .find(Locator::XPath("//*[contains(text(),'Local Node')]"))
-            .await?
-            .click()
-            .await?;
-
-        log::info!("click upload");
-        self.client
-            .wait_for_find(Locator::XPath(
-                "//*[contains(text(),'Upload & Instantiate Contract')]",
-            ))
-            .await?
-            .click()
-            .await?;
-
-        log::info!("injecting jquery");
-        let inject = String::from(
-            "(function (){\
-                    var d = document;\
-                    if (!d.getElementById('jquery')) {\
-                        var s = d.createElement('script');\
-                        s.src = 'https://code.jquery.com/jquery-3.6.0.min.js';\
-                        s.id = 'jquery';\
-                        d.body.appendChild(s);\
-                        (function() {\
-                            var nTimer = setInterval(function() {\
-                                if (window.jQuery) {\
-                                    $('body').append('<div id=\"jquery-ready\"></div');\
-                                    clearInterval(nTimer);\
-                                }\
-                            }, 100);\
-                        })();\
-                    }\
-                })();",
-        );
-        self.client.execute(&*inject, Vec::new()).await?;
-
-        log::info!("waiting for jquery");
-        self.client
-            .wait_for_find(Locator::Css("#jquery-ready"))
-            .await?;
-
-        log::info!("click combobox");
-        self.client
-            .execute("$('[role=combobox]').click()", Vec::new())
-            .await?;
-
-        log::info!("click alice");
-        self.client
-            .execute("$('[name=alice]').click()", Vec::new())
-            .await?;
-
-        log::info!("uploading {:?}", upload_input.contract_path);
-        let mut upload = self
-            .client
-            .find(Locator::Css(".ui--InputFile input"))
-            .await?;
-        upload
-            .send_keys(&upload_input.contract_path.display().to_string())
-            .await?;
-        self.client
-            .execute("$(\".ui--InputFile input\").trigger('change')", Vec::new())
-            .await?;
-
-        // I used this for local debugging to make tooltips disappear if the cursor
-        // was placed unfortunately.
-        // log::info!("click sidebar");
-        // self.client
-        // .wait_for_find(Locator::XPath(
-        // "//div[@class = 'app--SideBar']",
-        // ))
-        // .await?
-        // .click();
-
-        log::info!("click settings");
-        self.client
-            .find(Locator::Css(".app--SideBar-settings"))
-            .await?
-            .click()
-            .await?;
-        log::info!("click settings");
-        self.client
-            .find(Locator::Css(".app--SideBar-settings"))
-            .await?
-            .click()
-            .await?;
+        let mut upload_page =
+            UploadPage::open(&self.client, &selectors, &self.page("/upload")).await?;
+        upload_page.skip_intro().await?;
 
-        // We should get rid of this `sleep`
-        std::thread::sleep(std::time::Duration::from_millis(500));
+        // We don't select the "Local Node" in the settings here, the node is already
+        // chosen via the `rpc` query parameter of the URL.
 
-        log::info!("click details");
-        self.client
-            .wait_for_find(Locator::XPath(
-                "//*[contains(text(),'Constructor Details')]",
-            ))
-            .await?
-            .click()
+        upload_page.open_upload_form().await?;
+        upload_page
+            .select_deployment_account(Account::alice().name())
             .await?;
+        upload_page.upload(&upload_input.contract_path).await?;
 
-        if let Some(caller) = upload_input.caller {
-            // open listbox for accounts
-            log::info!("click listbox for accounts");
-            self.client
-                .wait_for_find(Locator::XPath(
-                    "//*[contains(text(),'instantiation account')]/ancestor::div[1]/div",
-                ))
-                .await?
-                .click()
-                .await?;
-
-            // choose caller
-            log::info!("choose {:?}", caller);
-            let path = format!("//div[@name = '{}']", caller.to_lowercase());
-            self.client
-                .find(Locator::XPath(&path))
-                .await?
-                .click()
-                .await?;
-        }
-
-        for (key, value) in upload_input.initial_values.iter() {
-            log::info!("inserting '{}' into input field '{}'", value, key);
-            let path = format!(
-                "//label/*[contains(text(),'{}')]/ancestor::div[1]//*/input",
-                key
-            );
-            let mut input = self.client.find(Locator::XPath(&path)).await?;
-            // we need to clear the default `0x000...` input from the field
-            input.clear().await?;
-            input.send_keys(&value).await?;
-        }
-
-        if let Some(constructor) = upload_input.constructor {
-            log::info!("click constructor list box");
-            self.client
-                .wait_for_find(Locator::XPath(
-                    "//label/*[contains(text(),'Instantiation Constructor')]/ancestor::div[1]//*/div[@role='listbox']"
-                ))
-                .await?.click().await?;
-
-            log::info!("click constructor option {}", constructor);
-            let path = format!(
-                "//span[@class = 'ui--MessageSignature-name' and contains(text(),'{}')]",
-                constructor
-            );
-            self.client
-                .wait_for_find(Locator::XPath(&path))
-                .await?
-                .click()
-                .await?;
-        }
-
-        log::info!("set endowment to {}", upload_input.endowment);
-        let mut input = self
-            .client
-            .find(Locator::XPath(
-                "//label/*[contains(text(),'Endowment')]/ancestor::div[1]//*/input",
-            ))
-            .await?;
-        input.clear().await?;
-        input.send_keys(&upload_input.endowment).await?;
+        let instantiate_page = InstantiatePage::new(&self.client, &selectors);
+        self.instantiate(instantiate_page, upload_input, bundle)
+            .await
+    }
 
-        log::info!("click endowment list box");
-        self.client
-            .wait_for_find(Locator::XPath("//label/*[contains(text(),'Endowment')]/ancestor::div[1]//*/div[@role='listbox']"))
-            .await?;
+    /// Instantiates the code `code_hash`, which the UI knows from an earlier upload.
+    async fn execute_instantiate(
+        &mut self,
+        code_hash: &str,
+        upload_input: Upload,
+    ) -> Result<String, Error> {
+        let bundle = ContractBundle::from_path(&upload_input.contract_path)?;
+        validation::check_upload(&bundle, &upload_input)
+            .map_err(Error::UploadRejected)?;
+        let selectors = self.selectors().await.map_err(Error::Other)?;
+
+        let url = self.page(&format!("/instantiate/{}", code_hash));
+        let instantiate_page =
+            InstantiatePage::open(&self.client, &selectors, &url).await?;
+        self.instantiate(instantiate_page, upload_input, bundle)
+            .await
+    }
 
+    /// Stores the code of the contract behind `contract_path` through the
+    /// "Upload & Instantiate" flow and returns its code hash.
+    async fn execute_code_upload(
+        &mut self,
+        upload_input: Upload,
+    ) -> Result<String, Error> {
+        let code_hash = ContractBundle::from_path(&upload_input.contract_path)?
+            .code_hash()
+            .to_string();
         log::info!(
-            "click endowment unit option {}",
-            upload_input.endowment_unit
+            "storing the code {} of {:?}",
+            code_hash,
+            upload_input.contract_path
         );
-        let path = format!(
-            "//div[@role='option']/span[contains(text(),'{}')]",
-            upload_input.endowment_unit
+        let addr = self.execute_upload(upload_input.default_values()).await?;
+        log::info!(
+            "the code {} was stored with the contract {}",
+            code_hash,
+            addr
         );
-        self.client.wait_for_find(Locator::XPath(&path)).await?;
-
-        log::info!("Check 'Unique Instantiation Salt' checkbox");
-        let path = "//*[contains(text(),'Unique Instantiation Salt')]/ancestor::div[1]//div[contains(@class, 'ui--Toggle')]/div";
-        self.client
-            .find(Locator::XPath(path))
-            .await?
-            .click()
-            .await?;
-
-        log::info!("click instantiate");
-        self.client
-            .find(Locator::XPath("//button[contains(text(),'Instantiate')]"))
-            .await?
-            .click()
-            .await?;
-
-        log::info!("click sign and submit");
-        self.client
-            .wait_for_find(Locator::XPath("//button[contains(text(),'Sign & Submit')]"))
-            .await?
-            .click()
-            .await?;
-
-        // h1: Contract successfully instantiated
-        self.client
-            .wait_for_find(Locator::XPath(
-                "//*[contains(text(),'Contract successfully instantiated')]",
-            ))
-            .await?;
-
-        log::info!("click dismiss");
-        self.client
-            .wait_for_find(Locator::XPath(
-                "//*[contains(text(),'Dismiss all notifications')]",
-            ))
-            .await?
-            .click()
-            .await?;
-
-        // wait for disappearance animation to finish instead
-        // otherwise the notifications might occlude buttons
-        log::info!("wait for animation to finish");
-        self.client
-            .execute("$('.ui--Status').hide()", Vec::new())
-            .await?;
-
-        log::info!("click execute");
-        self.client
-            .find(Locator::XPath(
-                "//button[contains(text(),'Execute Contract')]",
-            ))
-            .await?
-            .click()
-            .await?;
-
-        let base_url = url("");
-        let re = Regex::new(&format!("{}/#/execute/([0-9a-zA-Z]+)/0", base_url))
-            .expect("invalid regex");
-        let curr_client_url = self.client.current_url().await?;
-        let captures = re
-            .captures(curr_client_url.as_str())
-            .expect("contract address cannot be extracted from website");
-        let addr = captures.get(1).expect("no capture group").as_str();
-        log::info!("contract address {:?}", addr);
-        Ok(String::from(addr))
+        Ok(code_hash)
     }
 
     /// Executes the RPC call `call`.
@@ -400,109 +507,47 @@ impl CanvasUi {
     ///
     /// This method must not make any assumptions about the state of the Ui before
     /// the method is invoked. It must e.g. open the upload page right at the start.
-    pub async fn execute_rpc(
-        &mut self,
-        call: Call,
-    ) -> Result<String, Box<dyn std::error::Error>> {
-        let url = format!("{}{}/0", url("/#/execute/"), call.contract_address);
-        self.client.goto(url.as_str()).await?;
-
-        // open listbox for methods
-        log::info!("click listbox");
-        self.client
-            .find(Locator::XPath(
-                "//*[contains(text(),'Message to Send')]/ancestor::div[1]/div",
-            ))
-            .await?
-            .click()
-            .await?;
-
-        // click `method`
-        log::info!("choose {:?}", call.method);
-        let path = format!("//*[contains(text(),'Message to Send')]/ancestor::div[1]/div//*[contains(text(),'{}')]", call.method);
-        self.client
-            .find(Locator::XPath(&path))
-            .await?
-            .click()
-            .await?;
-
-        // Open listbox
-        log::info!("Open listbox for rpc vs. transaction");
-        let path = "//*[contains(text(),'Send as RPC call')]/ancestor::div[1]/ancestor::div[1]/ancestor::div[1]";
-        self.client
-            .find(Locator::XPath(path))
-            .await?
-            .click()
-            .await?;
-
-        // Send as RPC call
-        log::info!("select 'Send as RPC call'");
-        let path = "//*[contains(text(),'Send as RPC call')]/ancestor::div[1]";
-        self.client
-            .find(Locator::XPath(path))
-            .await?
-            .click()
-            .await?;
-
-        // possibly set max gas
-        if let Some(max_gas) = call.max_gas_allowed {
-            // click checkbox
-            log::info!("unset 'use estimated gas' checkbox if it exists");
-            let path = "//*[contains(text(),'use estimated gas')]/ancestor::div[1]/div";
-            let checkbox = self.client.find(Locator::XPath(path)).await;
-
-            if let Ok(checkbox) = checkbox {
-                log::info!("unsetting 'use estimated gas' checkbox - it exists");
-                checkbox.click().await?;
-            }
-
-            log::info!("{}", &format!("entering max gas {:?}", max_gas));
-            let path = "//*[contains(text(),'Max Gas Allowed')]/ancestor::div[1]/div//input[@type = 'text']";
-            self.client
-                .find(Locator::XPath(path))
-                .await?
-                .clear()
-                .await?;
-            self.client
-                .find(Locator::XPath(path))
-                .await?
-                .send_keys(&max_gas)
+    async fn execute_rpc(&mut self, call: Call) -> Result<RpcOutcome, Error> {
+        self.check_call(&call, CallKind::Rpc)
+            .map_err(Error::InvalidCall)?;
+        let selectors = self.selectors().await.map_err(Error::Other)?;
+
+        let url = self.page(&format!("/execute/{}/0", call.contract_address));
+        let mut execute_page = ExecutePage::open(&self.client, &selectors, &url).await?;
+        execute_page.select_message(&call.method).await?;
+        execute_page.send_as_rpc().await?;
+        if let Some(max_gas) = &call.max_gas_allowed {
+            execute_page.set_max_gas(max_gas).await?;
+        }
+        for (key, value) in call.values.iter() {
+            execute_page
+                .set_argument(key, &format!("{}\n", value))
                 .await?;
         }
-
-        // possibly add values
-        for (key, mut value) in call.values {
-            log::info!("{}", &format!("entering {:?} into {:?}", &value, &key));
-            let path = format!(
-                "//*[contains(text(),'{}')]/ancestor::div[1]/div//input[@type = 'text']",
-                key
+        execute_page.call().await?;
+        let text = execute_page.outcome().await?;
+
+        // the UI shortens long values, the full value is read from the node
+        let bundle = match self.contracts.get(&call.contract_address) {
+            Some(bundle) => bundle,
+            None => {
+                log::info!(
+                    "the metadata of {:?} is unknown, the return value can't be decoded",
+                    call.contract_address
+                );
+                return Ok(RpcOutcome::new(None, text))
+            }
+        };
+        let value = node_rpc::dry_run(&self.chain, bundle, &call).await?;
+        let expected = RpcOutcome::from_value(value.clone());
+        if expected.text() != text.trim() {
+            log::warn!(
+                "the UI displays {:?}, but the node returned {:?}",
+                text,
+                expected.text()
             );
-            self.client
-                .find(Locator::XPath(&path))
-                .await?
-                .clear()
-                .await?;
-            value.push('\n');
-            self.client
-                .find(Locator::XPath(&path))
-                .await?
-                .send_keys(&value)
-                .await?
         }
-
-        // click call
-        log::info!("click call");
-        self.client
-            .find(Locator::XPath("//button[contains(text(),'Call')]"))
-            .await?
-            .click()
-            .await?;
-
-        // wait for outcomes
-        let mut el = self.client.wait_for_find(Locator::XPath("//div[@class = 'outcomes']/*[1]//div[@class = 'ui--output monospace']/div[1]")).await?;
-        let txt = el.text().await?;
-        log::info!("outcomes value {:?}", txt);
-        Ok(txt)
+        Ok(RpcOutcome::new(value, text))
     }
 
     /// Executes the transaction `call`.
@@ -511,219 +556,37 @@ impl CanvasUi {
     ///
     /// This method must not make any assumptions about the state of the Ui before
     /// the method is invoked. It must e.g. open the upload page right at the start.
-    pub async fn execute_transaction(&mut self, call: Call) -> Result<Events, Error> {
-        let url = format!("{}{}/0", url("/#/execute/"), call.contract_address);
-        self.client.goto(url.as_str()).await?;
-        self.client.refresh().await?;
-
-        // open listbox for methods
-        log::info!("click listbox");
-        self.client
-            .wait_for_find(Locator::XPath(
-                "//*[contains(text(),'Message to Send')]/ancestor::div[1]/div",
-            ))
-            .await?
-            .click()
-            .await?;
-
-        // click `method`
-        log::info!("choose {:?}", call.method);
-        let path = format!("//*[contains(text(),'Message to Send')]/ancestor::div[1]/div//*[contains(text(),'{}')]", call.method);
-        self.client
-            .find(Locator::XPath(&path))
-            .await?
-            .click()
-            .await?;
-
-        // Open listbox
-        log::info!("open listbox for rpc vs. transaction");
-        let path = "//*[contains(text(),'Send as transaction')]/ancestor::div[1]/ancestor::div[1]/ancestor::div[1]";
-        self.client
-            .find(Locator::XPath(path))
-            .await?
-            .click()
-            .await?;
-
-        // Send as transaction
-        log::info!("select 'Send as transaction'");
-        let path = "//*[contains(text(),'Send as transaction')]/ancestor::div[1]";
-        self.client
-            .find(Locator::XPath(path))
-            .await?
-            .click()
-            .await?;
-
-        if let Some(caller) = call.caller {
-            // open listbox for accounts
-            log::info!("click listbox for accounts");
-            self.client
-                .wait_for_find(Locator::XPath(
-                    "//*[contains(text(),'Call from Account')]/ancestor::div[1]/div",
-                ))
-                .await?
-                .click()
-                .await?;
-
-            // choose caller
-            log::info!("choose {:?}", caller);
-            let path = format!("//div[@name = '{}']", caller.to_lowercase());
-            self.client
-                .find(Locator::XPath(&path))
-                .await?
-                .click()
-                .await?;
+    async fn execute_transaction(&mut self, call: Call) -> Result<Events, Error> {
+        self.check_call(&call, CallKind::Transaction)
+            .map_err(Error::InvalidCall)?;
+        let selectors = self.selectors().await.map_err(Error::Other)?;
+
+        let url = self.page(&format!("/execute/{}/0", call.contract_address));
+        let mut execute_page = ExecutePage::open(&self.client, &selectors, &url).await?;
+        execute_page.refresh().await?;
+        execute_page.select_message(&call.method).await?;
+        execute_page.send_as_transaction().await?;
+        if let Some(caller) = &call.caller {
+            execute_page.select_caller(caller.name()).await?;
         }
-
-        // Possibly add payment
         if let Some(payment) = call.payment {
-            // Open listbox
-            log::info!("open listbox for payment units");
-            let path = format!("//*[contains(text(),'{}')]/ancestor::div[1]/ancestor::div[1]/ancestor::div[1]", payment.unit);
-            self.client
-                .find(Locator::XPath(&path))
-                .await?
-                .click()
-                .await?;
-
-            log::info!("click payment unit option {}", payment.unit);
-            let path = format!(
-                "//div[@role='option']/span[contains(text(),'{}')]/ancestor::div[1]",
-                payment.unit
-            );
-            self.client
-                .wait_for_find(Locator::XPath(&path))
-                .await?
-                .click()
-                .await?;
-
-            log::info!("{}", &format!("entering payment {:?}", payment.payment));
-            let path = "//*[contains(text(),'Payment')]/ancestor::div[1]/div//input[@type = 'text']";
-            self.client
-                .find(Locator::XPath(path))
-                .await?
-                .clear()
-                .await?;
-            self.client
-                .find(Locator::XPath(path))
-                .await?
-                .send_keys(&payment.payment)
-                .await?;
+            let (payment, unit) = payment.input();
+            execute_page.set_payment(&payment, unit.name()).await?;
         }
-
-        // possibly set max gas
         if let Some(max_gas) = call.max_gas_allowed {
-            // click checkbox
-            log::info!("unset 'use estimated gas' checkbox");
-            let path = "//*[contains(text(),'use estimated gas')]/ancestor::div[1]/div";
-            self.client
-                .find(Locator::XPath(path))
-                .await?
-                .click()
-                .await?;
-
-            log::info!("{}", &format!("entering max gas {:?}", max_gas));
-            let path = "//*[contains(text(),'Max Gas Allowed')]/ancestor::div[1]/div//input[@type = 'text']";
-            self.client
-                .find(Locator::XPath(path))
-                .await?
-                .clear()
-                .await?;
-            self.client
-                .find(Locator::XPath(path))
-                .await?
-                .send_keys(&max_gas)
-                .await?;
+            execute_page.set_max_gas(&max_gas).await?;
         }
-
-        // possibly add values
         for (key, value) in call.values {
-            log::info!("{}", &format!("entering {:?} into {:?}", &value, &key));
-            let path = format!(
-                "//*[contains(text(),'{}')]/ancestor::div[1]/div//input[@type = 'text']",
-                key
-            );
-            self.client
-                .find(Locator::XPath(&path))
-                .await?
-                .clear()
-                .await?;
-            self.client
-                .find(Locator::XPath(&path))
-                .await?
-                .send_keys(&value)
-                .await?;
-        }
-
-        // click call
-        log::info!("click call");
-        self.client
-            .find(Locator::XPath("//button[contains(text(),'Call')]"))
-            .await?
-            .click()
-            .await?;
-
-        // wait for notification to show up
-        self.client
-            .wait_for_find(Locator::XPath(
-                "//div[@class = 'status' and contains(text(), 'queued')]",
-            ))
-            .await?;
-
-        // click sign and submit
-        log::info!("sign and submit");
-        self.client
-            .find(Locator::XPath("//button[contains(text(),'Sign & Submit')]"))
-            .await?
-            .click()
-            .await?;
-
-        // maybe assert?
-        log::info!("waiting for either success or failure notification");
-        self.client.wait_for_find(
-            Locator::XPath("//div[@class = 'status']/ancestor::div/div[@class = 'header' and (contains(text(), 'ExtrinsicSuccess') or contains(text(), 'ExtrinsicFailed'))]")
-        ).await?;
-
-        // extract all status messages
-        let statuses = self
-            .client
-            .find_all(Locator::XPath(
-                "//div[contains(@class, 'ui--Status')]//div[@class = 'desc']",
-            ))
-            .await?;
-        log::info!("found {:?} status messages", statuses.len());
-        let mut statuses_processed = Vec::new();
-        for mut el in statuses {
-            let header = el
-                .find(Locator::XPath("div[@class = 'header']"))
-                .await?
-                .text()
-                .await?;
-            let status = el
-                .find(Locator::XPath("div[@class = 'status']"))
-                .await?
-                .text()
-                .await?;
-            log::info!("found status message {:?} with {:?}", header, status);
-            statuses_processed.push(Event { header, status });
-        }
-        let events = Events::new(statuses_processed);
-
-        self.client
-            .wait_for_find(Locator::XPath(
-                "//*[contains(text(),'Dismiss all notifications')]",
-            ))
-            .await?
-            .click()
-            .await?;
-
-        let success = events.contains("system.ExtrinsicSuccess");
-        let failure = events.contains("system.ExtrinsicFailed");
-        match (success, failure) {
-            (true, false) => Ok(events),
-            (false, true) => Err(Error::ExtrinsicFailed(events)),
-            (false, false) => panic!("ERROR: Neither 'ExtrinsicSuccess' nor 'ExtrinsicFailed' was found in status messages!"),
-            (true, true) => panic!("ERROR: Both 'ExtrinsicSuccess' nor 'ExtrinsicFailed' was found in status messages!"),
+            execute_page.set_argument(&key, &value).await?;
         }
+        execute_page.call().await?;
+
+        let mut notifications = NotificationsPanel::new(&self.client, &selectors);
+        notifications.wait_until_queued().await?;
+        let since = self.chain.best_block_number().await.map_err(Error::Other)?;
+        let password = call.caller.as_ref().and_then(Account::password);
+        execute_page.sign_and_submit(password).await?;
+        self.extrinsic_events(&mut notifications, since).await
     }
 }
 
@@ -745,204 +608,22 @@ impl Drop for CanvasUi {
     }
 }
 
-#[derive(Debug)]
-pub enum Error {
-    ExtrinsicFailed(Events),
-    Other(Box<dyn std::error::Error>),
-}
-
-impl From<CmdError> for Error {
-    fn from(cmd_err: CmdError) -> Self {
-        Error::Other(Box::new(cmd_err))
-    }
-}
-
-#[derive(Debug)]
-pub struct Payment {
-    /// The payment.
-    payment: String,
-    /// The unit of payment.
-    unit: String,
-}
-
-#[derive(Debug)]
-pub struct Event {
-    /// The header text returned in a status event by the UI.
-    header: String,
-    /// The status text returned in a status event by the UI.
-    status: String,
-}
-
-#[derive(Debug)]
-pub struct Events {
-    /// The events returned by the UI as a result of a RPC call or a transaction.
-    events: Vec<Event>,
-}
-
-impl Events {
-    /// Creates a new `Events` instance.
-    pub fn new(events: Vec<Event>) -> Self {
-        Self { events }
-    }
-
-    /// Returns `true` if the `event` is contained in these events.
-    pub fn contains(&self, event: &str) -> bool {
-        self.events
-            .iter()
-            .any(|evt| evt.header == event || evt.status == event)
-    }
-}
-
-pub struct Call {
-    /// Address of the contract.
-    contract_address: String,
-    /// Method to execute.
-    method: String,
-    /// Maximum gas allowed.
-    max_gas_allowed: Option<String>,
-    /// Values to pass along.
-    values: Vec<(String, String)>,
-    /// The payment to send with the call.
-    payment: Option<Payment>,
-    /// The account from which to execute the call.
-    caller: Option<String>,
-}
-
-impl Call {
-    /// Creates a new `Transaction` instance.
-    pub fn new(contract_address: &str, method: &str) -> Self {
-        Self {
-            contract_address: contract_address.to_string(),
-            method: method.to_string(),
-            max_gas_allowed: None,
-            values: Vec::new(),
-            payment: None,
-            caller: None,
-        }
-    }
-
-    /// Adds an initial value.
-    pub fn push_value(mut self, key: &str, val: &str) -> Self {
-        self.values.push((key.to_string(), val.to_string()));
-        self
-    }
-
-    /// Sets the maximum gas allowed.
-    pub fn max_gas(mut self, max_gas: &str) -> Self {
-        self.max_gas_allowed = Some(max_gas.to_string());
-        self
-    }
-
-    /// Sets the payment submitted with the call.
-    pub fn payment(mut self, payment: &str, unit: &str) -> Self {
-        self.payment = Some(Payment {
-            payment: payment.to_string(),
-            unit: unit.to_string(),
-        });
-        self
-    }
-
-    /// Sets the account from which to execute the call.
-    pub fn caller(mut self, caller: &str) -> Self {
-        self.caller = Some(caller.to_string());
-        self
-    }
-}
-
-pub struct Upload {
-    /// Path to the contract which should be uploaded.
-    contract_path: PathBuf,
-    /// Values to instantiate the contract with.
-    initial_values: Vec<(String, String)>,
-    /// Initial endowment of the contract.
-    endowment: String,
-    /// Unit for initial endowment of the contract.
-    endowment_unit: String,
-    /// Maximum allowed gas.
-    #[allow(dead_code)]
-    max_allowed_gas: String,
-    /// The constructor to use. If not specified the default selected one is used.
-    constructor: Option<String>,
-    /// The caller to use. If not specified the default selected one is used.
-    caller: Option<String>,
-}
-
-impl Upload {
-    /// Creates a new `Upload` instance.
-    pub fn new(contract_path: PathBuf) -> Self {
-        Self {
-            contract_path,
-            initial_values: Vec::new(),
-            endowment: "1000".to_string(),
-            endowment_unit: "Unit".to_string(),
-            max_allowed_gas: "5000".to_string(),
-            constructor: None,
-            caller: None,
-        }
-    }
-
-    /// Adds an initial value.
-    pub fn push_initial_value(mut self, key: &str, val: &str) -> Self {
-        self.initial_values.push((key.to_string(), val.to_string()));
-        self
-    }
-
-    /// Sets the contract path.
-    #[allow(dead_code)]
-    pub fn contract_path(mut self, path: PathBuf) -> Self {
-        self.contract_path = path;
-        self
-    }
-
-    /// Sets the initial endowment.
-    pub fn endowment(mut self, endowment: &str, unit: &str) -> Self {
-        self.endowment = endowment.to_string();
-        self.endowment_unit = unit.to_string();
-        self
-    }
-
-    /// Sets the max allowed gas.
-    #[allow(dead_code)]
-    pub fn max_allowed_gas(mut self, max: &str) -> Self {
-        self.max_allowed_gas = max.to_string();
-        self
-    }
-
-    /// Sets the constructor to use for instantiation.
-    pub fn constructor(mut self, constructor: &str) -> Self {
-        self.constructor = Some(constructor.to_string());
-        self
-    }
-
-    /// Sets the caller to use for instantiation.
-    pub fn caller(mut self, caller: &str) -> Self {
-        self.caller = Some(caller.to_string());
-        self
-    }
-}
-
-/// Asserts that the `canvas` process is running.
-fn assert_canvas_node_running() {
-    let processes = processes().expect("can't get processes");
-    let canvas_node_running = processes
-        .into_iter()
-        .filter_map(|pr| pr.ok())
-        .map(|p| p.cmdline())
-        .filter_map(|cmdline| cmdline.ok())
-        .filter_map(|opt| opt)
-        .any(|str| str.contains("canvas ") || str.contains("canvas-rand-extension "));
-    assert!(
-        canvas_node_running,
-        "ERROR: The canvas node is not running!"
-    );
-}
-
 /// Returns the URL to the `path` in the Canvas UI.
 ///
-/// Defaults to https://paritytech.github.io/canvas-ui as the base URL.
+/// If `CANVAS_UI_BUILD` is set, the build in this directory is served locally.
+/// Otherwise defaults to https://paritytech.github.io/canvas-ui as the base URL.
 fn url(path: &str) -> String {
-    let base_url: String = std::env::var("CANVAS_UI_URL")
-        .unwrap_or(String::from("https://paritytech.github.io/canvas-ui"));
+    let base_url: String = match std::env::var("CANVAS_UI_BUILD") {
+        Ok(build_dir) => {
+            ui_server::serve(Path::new(&build_dir))
+                .expect("unable to serve the canvas-ui build")
+                .to_string()
+        }
+        Err(_) => {
+            std::env::var("CANVAS_UI_URL")
+                .unwrap_or(String::from("https://paritytech.github.io/canvas-ui"))
+        }
+    };
 
     // strip a possibly ending `/` from he URL, since a URL like `http://foo//bar`
     // can cause issues.
@@ -951,6 +632,36 @@ fn url(path: &str) -> String {
     String::from(format!("{}{}", base_url, path))
 }
 
+/// Encodes `component` for use in a URL, like `encodeURIComponent` in JavaScript.
+fn encode_uri_component(component: &str) -> String {
+    component
+        .chars()
+        .map(|c| {
+            match c {
+                'A'..='Z'
+                | 'a'..='z'
+                | '0'..='9'
+                | '-'
+                | '_'
+                | '.'
+                | '!'
+                | '~'
+                | '*'
+                | '\''
+                | '('
+                | ')' => c.to_string(),
+                c => {
+                    let mut buf = [0; 4];
+                    c.encode_utf8(&mut buf)
+                        .bytes()
+                        .map(|b| format!("%{:02X}", b))
+                        .collect()
+                }
+            }
+        })
+        .collect()
+}
+
 /// Returns `true` if the shutdown procedure should be executed after a test run.
 /// This mostly involves closing the browser.
 ///
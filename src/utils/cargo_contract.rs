@@ -12,29 +12,160 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use super::build_cache;
 use regex::Regex;
+use serde::{
+    Deserialize,
+    Serialize,
+};
 use std::{
-    path::PathBuf,
-    process::Command,
+    io::{
+        self,
+        BufRead,
+        BufReader,
+        Read,
+        Write,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+    process::{
+        Command,
+        Output,
+        Stdio,
+    },
+    thread,
+    time::{
+        Duration,
+        Instant,
+    },
 };
 
+/// The artifacts of a successful contract build.
+///
+/// They are serialized into the entries of the `build_cache`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildArtifacts {
+    /// Path to the `.contract` bundle (code + metadata).
+    pub contract_bundle: PathBuf,
+    /// Path to the optimized `.wasm` file.
+    pub wasm: PathBuf,
+    /// Path to the `metadata.json`.
+    pub metadata: PathBuf,
+    /// The size of the Wasm before and after the optimization, if `cargo-contract`
+    /// reported it.
+    pub wasm_size: Option<WasmSize>,
+    /// How long the build took.
+    pub duration: Duration,
+}
+
+/// The size of the Wasm in KiB, as reported by `cargo-contract`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WasmSize {
+    /// The size before `wasm-opt` was run.
+    #[serde(rename = "original_size")]
+    pub original: f64,
+    /// The size after `wasm-opt` was run.
+    #[serde(rename = "optimized_size")]
+    pub optimized: f64,
+}
+
+/// The result which `cargo contract build --output-json` prints.
+///
+/// Only the fields we are interested in are deserialized.
+#[derive(Deserialize)]
+struct JsonBuildResult {
+    dest_wasm: Option<PathBuf>,
+    metadata_result: Option<JsonMetadataResult>,
+    optimization_result: Option<WasmSize>,
+}
+
+/// The `metadata_result` of `JsonBuildResult`.
+#[derive(Deserialize)]
+struct JsonMetadataResult {
+    dest_metadata: PathBuf,
+    dest_bundle: PathBuf,
+}
+
+/// Builds the contract at `manifest_path` using `cargo +nightly contract`.
+///
+/// The artifacts are cached, a contract is only built again if its sources or
+/// the toolchain changed (see `build_cache`).
+///
+/// The build runs on a blocking thread, so that the timeout of the test covers
+/// it. A build which exceeds the timeout can't be cancelled, it still runs to
+/// its end while the test fails.
+pub(crate) async fn build(manifest_path: &Path) -> Result<BuildArtifacts, String> {
+    let manifest_path = manifest_path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        build_cache::cached(&manifest_path, build_uncached)
+    })
+    .await
+    .map_err(|err| format!("the build task failed: {:?}", err))?
+}
+
 /// Builds the contract at `manifest_path` using `cargo +nightly contract`.
 ///
-/// If successful, returns the path to the `.contract` file.
-pub(crate) fn build(manifest_path: &PathBuf) -> Result<PathBuf, String> {
+/// The machine-readable output of `cargo-contract` (`--output-json`) is used to
+/// find the artifacts. For versions of `cargo-contract` which don't support it yet,
+/// the paths are extracted from the human-readable output instead.
+fn build_uncached(manifest_path: &Path) -> Result<BuildArtifacts, String> {
     assert_wasm_opt_available();
 
-    let mut dir = manifest_path.clone();
+    let started = Instant::now();
+    let output = cargo_contract_build(manifest_path, true);
+    let artifacts = if output.status.success() {
+        let stdout = String::from_utf8(output.stdout).expect("string conversion failed");
+        parse_json_output(&stdout, started.elapsed())?
+    } else if String::from_utf8_lossy(&output.stderr).contains("--output-json") {
+        log::info!("`cargo-contract` does not support `--output-json`, falling back");
+        let output = cargo_contract_build(manifest_path, false);
+        if !output.status.success() {
+            return Err(failure(output))
+        }
+        let stdout = String::from_utf8(output.stdout).expect("string conversion failed");
+        parse_human_readable_output(&stdout, started.elapsed())?
+    } else {
+        return Err(failure(output))
+    };
+    log::info!(
+        "Built the contract bundle {:?} (wasm: {:?}, metadata: {:?}) in {:?}",
+        artifacts.contract_bundle,
+        artifacts.wasm,
+        artifacts.metadata,
+        artifacts.duration
+    );
+    if let Some(size) = artifacts.wasm_size {
+        log::info!(
+            "Original wasm size: {}K, optimized: {}K",
+            size.original,
+            size.optimized
+        );
+    }
+    Ok(artifacts)
+}
+
+/// Executes `cargo +nightly contract build` for the contract at `manifest_path`.
+fn cargo_contract_build(manifest_path: &Path, output_json: bool) -> Output {
+    let mut dir = manifest_path.to_path_buf();
     dir.pop(); // pop `Cargo.toml` from the path
 
-    let output = Command::new("cargo")
+    let mut command = Command::new("cargo");
+    command
         .arg("+nightly")
         .arg("contract")
         .arg("build")
-        .arg("--manifest-path=Cargo.toml")
+        .arg("--manifest-path=Cargo.toml");
+    if output_json {
+        command.arg("--output-json");
+    }
+    let mut child = command
         .current_dir(dir)
-        // we want to receive the child's output as part of the ink-waterfall stdout
-        .stdout(std::process::Stdio::piped())
+        // the json is read from stdout, the progress on stderr is forwarded to the
+        // ink-waterfall stderr as it comes
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
         .spawn()
         .map_err(|err| {
             format!(
@@ -42,43 +173,110 @@ pub(crate) fn build(manifest_path: &PathBuf) -> Result<PathBuf, String> {
                 manifest_path, err
             )
         })
-        .expect("failed to execute process")
-        .wait_with_output()
-        .expect("failed to receive output");
+        .expect("failed to execute process");
+    let stderr = child.stderr.take().expect("stderr is piped");
+    let forward_stderr = thread::spawn(move || forward(stderr));
+    let mut output = child.wait_with_output().expect("failed to receive output");
+    // it is still needed to find out whether `--output-json` is supported
+    output.stderr = forward_stderr.join().expect("forwarding stderr panicked");
+    output
+}
 
-    if output.status.success() {
-        let stdout = String::from_utf8(output.stdout).expect("string conversion failed");
-        // extract the path to the resulting `.contract` from the output
-        let re_path = Regex::new(
-            r"Your contract artifacts are ready. You can find them in:\n([A-Za-z0-9_\-/]+)\n"
-        )
-            .expect("invalid regex");
-        let captures = re_path
-            .captures(&stdout)
-            .ok_or("regex does not match the command output")
-            .map_err(|err| format!("{}: '{:?}'", err, stdout))?;
-        let directory = captures.get(1).expect("no capture group found").as_str();
-
-        // extract the basename to the resulting `.contract`
-        let re_basename =
-            Regex::new(r"\- ([A-Za-z0-9_\-/]+).contract \(code \+ metadata\)\n")
-                .expect("invalid regex");
-        let captures = re_basename
-            .captures(&stdout)
-            .ok_or("regex does not match the command output")
-            .map_err(|err| format!("{}: '{:?}'", err, stdout))?;
-        let basename = captures.get(1).expect("no capture group found").as_str();
-        let path = PathBuf::from(directory).join(format!("{}.contract", basename));
-        log::info!("Path to the resulting contract bundle: {:?}", path);
-        Ok(path)
-    } else {
-        let stderr = String::from_utf8(output.stderr).expect("string conversion failed");
-        Err(format!(
-            "Failed with exit code: {:?} and '{:?}'",
-            output.status.code(),
-            stderr
-        ))
+/// Writes everything read from `input` to the stderr of this process and
+/// returns it.
+fn forward(input: impl Read) -> Vec<u8> {
+    let mut input = BufReader::new(input);
+    let mut forwarded = Vec::new();
+    let mut line = Vec::new();
+    while let Ok(read) = input.read_until(b'\n', &mut line) {
+        if read == 0 {
+            break
+        }
+        let _ = io::stderr().write_all(&line);
+        forwarded.append(&mut line);
     }
+    forwarded
+}
+
+/// Returns the error for a failed `cargo-contract` invocation.
+fn failure(output: Output) -> String {
+    let stderr = String::from_utf8(output.stderr).expect("string conversion failed");
+    format!(
+        "Failed with exit code: {:?} and '{:?}'",
+        output.status.code(),
+        stderr
+    )
+}
+
+/// Extracts the artifacts from the output of `cargo contract build --output-json`.
+fn parse_json_output(stdout: &str, duration: Duration) -> Result<BuildArtifacts, String> {
+    // the json is preceded by the output of `cargo` in some versions
+    let json = stdout
+        .find('{')
+        .map(|start| &stdout[start..])
+        .ok_or_else(|| format!("no json found in the command output: '{:?}'", stdout))?;
+    let result: JsonBuildResult = serde_json::from_str(json).map_err(|err| {
+        format!("unable to parse the json output: {:?}: '{:?}'", err, stdout)
+    })?;
+    let metadata_result = result
+        .metadata_result
+        .ok_or_else(|| format!("no metadata was generated: '{:?}'", stdout))?;
+    let wasm = result
+        .dest_wasm
+        .ok_or_else(|| format!("no wasm was generated: '{:?}'", stdout))?;
+    Ok(BuildArtifacts {
+        contract_bundle: metadata_result.dest_bundle,
+        wasm,
+        metadata: metadata_result.dest_metadata,
+        wasm_size: result.optimization_result,
+        duration,
+    })
+}
+
+/// Extracts the artifacts from the human-readable output of `cargo contract build`.
+fn parse_human_readable_output(
+    stdout: &str,
+    duration: Duration,
+) -> Result<BuildArtifacts, String> {
+    // extract the path to the resulting `.contract` from the output
+    let re_path = Regex::new(
+        r"Your contract artifacts are ready. You can find them in:\n([A-Za-z0-9_\-/]+)\n",
+    )
+    .expect("invalid regex");
+    let captures = re_path
+        .captures(stdout)
+        .ok_or("regex does not match the command output")
+        .map_err(|err| format!("{}: '{:?}'", err, stdout))?;
+    let directory = captures.get(1).expect("no capture group found").as_str();
+
+    // extract the basename to the resulting `.contract`
+    let re_basename =
+        Regex::new(r"\- ([A-Za-z0-9_\-/]+).contract \(code \+ metadata\)\n")
+            .expect("invalid regex");
+    let captures = re_basename
+        .captures(stdout)
+        .ok_or("regex does not match the command output")
+        .map_err(|err| format!("{}: '{:?}'", err, stdout))?;
+    let basename = captures.get(1).expect("no capture group found").as_str();
+
+    // the sizes are optional, we don't fail the build if the wording changes
+    let re_size = Regex::new(r"Original wasm size: ([0-9.]+)K, Optimized: ([0-9.]+)K")
+        .expect("invalid regex");
+    let wasm_size = re_size.captures(stdout).and_then(|captures| {
+        Some(WasmSize {
+            original: captures.get(1)?.as_str().parse().ok()?,
+            optimized: captures.get(2)?.as_str().parse().ok()?,
+        })
+    });
+
+    let directory = PathBuf::from(directory);
+    Ok(BuildArtifacts {
+        contract_bundle: directory.join(format!("{}.contract", basename)),
+        wasm: directory.join(format!("{}.wasm", basename)),
+        metadata: directory.join("metadata.json"),
+        wasm_size,
+        duration,
+    })
 }
 
 /// Asserts that `wasm-opt` is available.
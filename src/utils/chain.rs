@@ -0,0 +1,401 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reads the state of the chain which the tests run against.
+//!
+//! The UI only displays the names of the events of an extrinsic, their fields
+//! are read from the chain. The same goes for the documentation of the errors
+//! with which extrinsics fail, and the balances of accounts.
+
+use super::{
+    backend::Error,
+    balance::AccountBalance,
+    events::{
+        self,
+        section,
+        DispatchError,
+        Event,
+        Events,
+    },
+    scale,
+};
+use codec::Decode;
+use frame_metadata::{
+    DecodeDifferent,
+    RuntimeMetadata,
+    RuntimeMetadataPrefixed,
+};
+use once_cell::sync::Lazy;
+use serde_json::{
+    json,
+    Value,
+};
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        Mutex,
+    },
+};
+use subxt::{
+    sp_core::twox_128,
+    sp_runtime::AccountId32,
+    system::{
+        AccountStoreExt,
+        Phase,
+    },
+    Client,
+    ClientBuilder,
+    ContractsTemplateRuntime,
+    Raw,
+};
+
+/// The version of the runtime metadata which can be decoded.
+const METADATA_VERSION: u8 = 13;
+
+/// The decoded metadata of the runtimes by the URL of their node, so that it is
+/// only read once per node.
+static METADATA: Lazy<Mutex<HashMap<String, Arc<Metadata>>>> =
+    Lazy::new(Default::default);
+
+/// A connection to the node of the chain.
+pub struct Chain {
+    client: Client<ContractsTemplateRuntime>,
+    /// The URL of the node.
+    node_url: String,
+}
+
+impl Chain {
+    /// Connects to the node at `node_url`.
+    pub async fn connect(node_url: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let client = ClientBuilder::<ContractsTemplateRuntime>::new()
+            .set_url(node_url)
+            .build()
+            .await?;
+        let chain = Self {
+            client,
+            node_url: node_url.to_string(),
+        };
+        match chain.metadata().await {
+            Ok(metadata) => events::set_signatures(&metadata.events),
+            Err(err) => log::warn!("the fields of events can't be decoded: {}", err),
+        }
+        Ok(chain)
+    }
+
+    /// Returns the number of the best block.
+    pub async fn best_block_number(&self) -> Result<u32, Box<dyn std::error::Error>> {
+        let header: Value = self
+            .client
+            .rpc_client()
+            .request("chain_getHeader", &[])
+            .await?;
+        let number = header
+            .get("number")
+            .and_then(Value::as_str)
+            .ok_or_else(|| format!("the header {} has no number", header))?;
+        Ok(u32::from_str_radix(number.trim_start_matches("0x"), 16)?)
+    }
+
+    /// Returns the free and reserved balance of `account` at the best block.
+    pub async fn account_balance(
+        &self,
+        account: &AccountId32,
+    ) -> Result<AccountBalance, Box<dyn std::error::Error>> {
+        let info = self.client.account(account, None).await?;
+        Ok(AccountBalance {
+            free: info.data.free,
+            reserved: info.data.reserved,
+        })
+    }
+
+    /// Returns the decoded events of the block `number` together with the index of
+    /// the extrinsic which emitted them, `None` for events which weren't emitted by
+    /// an extrinsic.
+    pub async fn block_events(
+        &self,
+        number: u32,
+    ) -> Result<Vec<(Option<u32>, Event)>, Box<dyn std::error::Error>> {
+        let hash: Value = self
+            .client
+            .rpc_client()
+            .request("chain_getBlockHash", &[json!(number)])
+            .await?;
+        let key = [twox_128(b"System"), twox_128(b"Events")].concat();
+        let storage: Option<String> = self
+            .client
+            .rpc_client()
+            .request(
+                "state_getStorage",
+                &[json!(format!("0x{}", hex::encode(key))), hash],
+            )
+            .await?;
+        let storage = match storage {
+            Some(storage) => hex::decode(storage.trim_start_matches("0x"))?,
+            None => return Ok(Vec::new()),
+        };
+        let events = self
+            .client
+            .events_decoder()
+            .decode_events(&mut &storage[..])?
+            .into_iter()
+            .map(|(phase, raw)| {
+                let extrinsic = match phase {
+                    Phase::ApplyExtrinsic(index) => Some(index),
+                    _ => None,
+                };
+                // the decoder returns the dispatch error of `system.ExtrinsicFailed`
+                // instead of the event
+                let event = match raw {
+                    Raw::Event(event) => Event::decode(&event),
+                    Raw::Error(err) => Event::extrinsic_failed(&err),
+                };
+                (extrinsic, event)
+            })
+            .collect();
+        Ok(events)
+    }
+
+    /// Returns the decoded events of the latest extrinsic after the block `since`
+    /// which emitted all events `names` (e.g. `balances.Transfer`).
+    ///
+    /// This finds the events of an extrinsic submitted via the UI, which only
+    /// displays the names of the events.
+    pub async fn find_extrinsic_events(
+        &self,
+        since: u32,
+        names: &[String],
+    ) -> Result<Option<Vec<Event>>, Box<dyn std::error::Error>> {
+        let best = self.best_block_number().await?;
+        for number in (since + 1..=best).rev() {
+            let events = self.block_events(number).await?;
+            let mut extrinsics: Vec<u32> =
+                events.iter().filter_map(|(i, _)| *i).collect();
+            extrinsics.dedup();
+            for extrinsic in extrinsics.into_iter().rev() {
+                let found: Vec<Event> = events
+                    .iter()
+                    .filter(|(i, _)| *i == Some(extrinsic))
+                    .map(|(_, event)| event.clone())
+                    .collect();
+                let emitted_all = names
+                    .iter()
+                    .all(|name| found.iter().any(|event| &event.name() == name));
+                if emitted_all {
+                    log::info!(
+                        "found the events of extrinsic {} in block {}",
+                        extrinsic,
+                        number
+                    );
+                    return Ok(Some(found))
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Executes `input_data` (the selector and the arguments of a message) on the
+    /// contract `dest` via the `contracts_call` RPC and returns the output of the
+    /// message. The effects of the message are not persisted.
+    pub async fn contracts_call(
+        &self,
+        origin: &str,
+        dest: &str,
+        gas_limit: u64,
+        input_data: &[u8],
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let request = json!({
+            "origin": origin,
+            "dest": dest,
+            "value": 0,
+            "gasLimit": gas_limit,
+            "inputData": format!("0x{}", hex::encode(input_data)),
+        });
+        let response: Value = self
+            .client
+            .rpc_client()
+            .request("contracts_call", &[request])
+            .await?;
+        log::info!("`contracts_call` returned {}", response);
+
+        // the result used to be returned as `success`, newer nodes return a `Result`
+        let output = response
+            .get("result")
+            .and_then(|result| result.get("Ok"))
+            .or_else(|| response.get("success"))
+            .and_then(|ok| ok.get("data"))
+            .and_then(Value::as_str)
+            .ok_or_else(|| format!("the RPC call failed: {}", response))?;
+        Ok(scale::decode_hex(output)?)
+    }
+
+    /// Returns the error with which the extrinsic that emitted `events` failed,
+    /// together with its documentation from the metadata of the runtime.
+    ///
+    /// If the documentation can't be read, the error is returned without it.
+    pub async fn dispatch_error(&self, events: &Events) -> DispatchError {
+        let mut dispatch_error = events.dispatch_error().unwrap_or_default();
+        match self.error_docs(&dispatch_error).await {
+            Ok(Some(docs)) => dispatch_error.docs = docs,
+            Ok(None) => {
+                log::warn!("the error {} is not in the metadata", dispatch_error)
+            }
+            Err(err) => {
+                log::warn!("unable to read the docs of {}: {}", dispatch_error, err)
+            }
+        }
+        dispatch_error
+    }
+
+    /// Returns the documentation of `dispatch_error` from the metadata of the
+    /// runtime, or `None` if the metadata doesn't contain the error.
+    async fn error_docs(
+        &self,
+        dispatch_error: &DispatchError,
+    ) -> Result<Option<String>, Error> {
+        let metadata = self.metadata().await?;
+        Ok(metadata
+            .errors
+            .get(&(dispatch_error.module.clone(), dispatch_error.error.clone()))
+            .cloned())
+    }
+
+    /// Returns the metadata of the runtime, it is read from the node the first
+    /// time it is needed.
+    pub async fn metadata(&self) -> Result<Arc<Metadata>, Error> {
+        if let Some(metadata) = METADATA
+            .lock()
+            .expect("the metadata cache is poisoned")
+            .get(&self.node_url)
+        {
+            return Ok(metadata.clone())
+        }
+        let metadata: String = self
+            .client
+            .rpc_client()
+            .request("state_getMetadata", &[])
+            .await
+            .map_err(|err| Error::Other(Box::new(err)))?;
+        let metadata =
+            scale::decode_hex(&metadata).map_err(|err| Error::Other(err.into()))?;
+        let metadata = Arc::new(Metadata::decode(&metadata)?);
+        METADATA
+            .lock()
+            .expect("the metadata cache is poisoned")
+            .insert(self.node_url.clone(), metadata.clone());
+        Ok(metadata)
+    }
+}
+
+/// The parts of the metadata of a runtime which the tests use.
+#[derive(Debug, Default)]
+pub struct Metadata {
+    /// The documentation of the errors by section and name, e.g.
+    /// `("contracts", "NotCallable")`.
+    errors: HashMap<(String, String), String>,
+    /// The names and type names of the fields of the events by section and name,
+    /// e.g. `("balances", "Transfer")`.
+    events: HashMap<(String, String), Vec<(String, String)>>,
+}
+
+impl Metadata {
+    /// Decodes the SCALE encoded metadata `encoded`, as returned by
+    /// `state_getMetadata`.
+    ///
+    /// Only version 13 of the metadata is supported, other versions fail with
+    /// `Error::Unsupported`.
+    fn decode(encoded: &[u8]) -> Result<Self, Error> {
+        // the metadata starts with the magic number `meta`, followed by the version
+        match encoded.get(4) {
+            Some(&METADATA_VERSION) => (),
+            version => {
+                return Err(Error::Unsupported(format!(
+                    "version {:?} of the runtime metadata, only version {} is supported",
+                    version, METADATA_VERSION
+                )))
+            }
+        }
+        let modules = match RuntimeMetadataPrefixed::decode(&mut &encoded[..])
+            .map_err(|err| Error::Other(Box::new(err)))?
+            .1
+        {
+            RuntimeMetadata::V13(metadata) => decoded(metadata.modules)?,
+            _ => unreachable!("the version of the metadata was checked above"),
+        };
+
+        let mut errors = HashMap::new();
+        let mut events = HashMap::new();
+        for module in modules {
+            let section = section(&decoded(module.name)?);
+            for event in module.event.map(decoded).transpose()?.unwrap_or_default() {
+                let types = decoded(event.arguments)?;
+                let docs = decoded(event.documentation)?;
+                let fields = field_names(&docs, types.len())
+                    .into_iter()
+                    .zip(types)
+                    .collect();
+                events.insert((section.clone(), decoded(event.name)?), fields);
+            }
+            for error in decoded(module.errors)? {
+                let docs = decoded(error.documentation)?
+                    .iter()
+                    .map(|line| line.trim())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                errors.insert((section.clone(), decoded(error.name)?), docs);
+            }
+        }
+        Ok(Self { errors, events })
+    }
+}
+
+/// Returns the names of the `count` fields of an event from its documentation
+/// `docs`, which either ends with a list like `\[from, to, value\]` or lists the
+/// fields like ``- `contract`: The contract that emitted the event.``
+///
+/// Fields whose names are not documented are named by their index, e.g. `0`.
+fn field_names(docs: &[String], count: usize) -> Vec<String> {
+    let docs = docs.join("\n").replace('\\', "");
+    let bracketed = docs.rfind('[').and_then(|start| {
+        let list = &docs[start + 1..];
+        list.find(']').map(|end| {
+            list[..end]
+                .split(',')
+                .map(|name| name.trim().to_string())
+                .collect::<Vec<_>>()
+        })
+    });
+    let listed = docs
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("- `")?.split('`').next())
+        .map(String::from)
+        .collect::<Vec<_>>();
+    bracketed
+        .into_iter()
+        .chain(Some(listed))
+        .find(|names| names.len() == count && names.iter().all(|name| !name.is_empty()))
+        .unwrap_or_else(|| (0..count).map(|index| index.to_string()).collect())
+}
+
+/// Returns the value of a `DecodeDifferent` which was decoded from the metadata.
+fn decoded<B, O>(value: DecodeDifferent<B, O>) -> Result<O, Error> {
+    match value {
+        DecodeDifferent::Decoded(value) => Ok(value),
+        DecodeDifferent::Encode(_) => {
+            Err(Error::Other(
+                "the metadata contains a value which wasn't decoded".into(),
+            ))
+        }
+    }
+}
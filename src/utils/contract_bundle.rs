@@ -0,0 +1,275 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A typed representation of the `.contract` bundle which `cargo-contract`
+//! generates.
+//!
+//! Both the metadata of the ink! release candidates (names are paths like
+//! `["new"]`) and of later versions (names are `label`s) are supported. Only the
+//! parts of the bundle which the tests use are parsed.
+
+use super::scale;
+use serde::{
+    Deserialize,
+    Deserializer,
+};
+use serde_json::Value;
+use std::{
+    fmt,
+    fs::File,
+    io::BufReader,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+/// An error while reading a contract bundle.
+#[derive(Debug)]
+pub enum BundleError {
+    /// The bundle file can not be read.
+    Read(PathBuf, std::io::Error),
+    /// The bundle is no valid JSON or does not have the expected structure.
+    Parse(PathBuf, serde_json::Error),
+    /// The bundle contains only the metadata, but no Wasm code.
+    MissingWasm(String),
+    /// The Wasm code in the bundle is no valid hex.
+    InvalidWasm(String, String),
+}
+
+impl fmt::Display for BundleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BundleError::Read(path, err) => {
+                write!(f, "unable to read the contract bundle {:?}: {}", path, err)
+            }
+            BundleError::Parse(path, err) => {
+                write!(f, "the contract bundle {:?} is malformed: {}", path, err)
+            }
+            BundleError::MissingWasm(name) => {
+                write!(f, "the bundle of {:?} contains no `source.wasm`", name)
+            }
+            BundleError::InvalidWasm(name, err) => {
+                write!(f, "the `source.wasm` of {:?} is invalid: {}", name, err)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BundleError {}
+
+/// The contents of a `.contract` file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContractBundle {
+    /// Information about the contract's code.
+    pub source: Source,
+    /// Information about the contract itself.
+    pub contract: Contract,
+    /// The constructors, messages and events of the contract.
+    pub spec: Spec,
+    /// The type registry which the `type` ids in the `spec` refer to.
+    #[serde(default)]
+    pub types: Vec<Value>,
+}
+
+impl ContractBundle {
+    /// Reads the contract bundle at `path`.
+    pub fn from_path(path: &Path) -> Result<Self, BundleError> {
+        let file =
+            File::open(path).map_err(|err| BundleError::Read(path.to_path_buf(), err))?;
+        serde_json::from_reader(BufReader::new(file))
+            .map_err(|err| BundleError::Parse(path.to_path_buf(), err))
+    }
+
+    /// Returns the hash of the contract's code, e.g. `0xa1b2…`.
+    pub fn code_hash(&self) -> &str {
+        &self.source.hash
+    }
+
+    /// Returns the contract's Wasm code.
+    pub fn wasm(&self) -> Result<Vec<u8>, BundleError> {
+        let wasm = self
+            .source
+            .wasm
+            .as_ref()
+            .ok_or_else(|| BundleError::MissingWasm(self.contract.name.clone()))?;
+        scale::decode_hex(wasm)
+            .map_err(|err| BundleError::InvalidWasm(self.contract.name.clone(), err))
+    }
+
+    /// Returns the constructor with the name `name`.
+    ///
+    /// If `name` is `None` the first one is returned, as it is preselected in the UI.
+    pub fn constructor(&self, name: Option<&str>) -> Option<&ConstructorSpec> {
+        match name {
+            Some(name) => {
+                self.spec
+                    .constructors
+                    .iter()
+                    .find(|constructor| constructor.label == name)
+            }
+            None => self.spec.constructors.first(),
+        }
+    }
+
+    /// Returns the message with the name `name`.
+    pub fn message(&self, name: &str) -> Option<&MessageSpec> {
+        self.spec
+            .messages
+            .iter()
+            .find(|message| message.label == name)
+    }
+}
+
+/// The `source` section of a contract bundle.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Source {
+    /// The hash of the Wasm code.
+    pub hash: String,
+    /// The hex encoded Wasm code, only part of the `.contract` bundle but not of
+    /// the `metadata.json`.
+    pub wasm: Option<String>,
+}
+
+/// The `contract` section of a contract bundle.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Contract {
+    /// The name of the contract.
+    pub name: String,
+}
+
+/// The `spec` section of a contract bundle.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Spec {
+    /// The constructors of the contract.
+    #[serde(default)]
+    pub constructors: Vec<ConstructorSpec>,
+    /// The messages of the contract.
+    #[serde(default)]
+    pub messages: Vec<MessageSpec>,
+    /// The events which the contract can emit.
+    #[serde(default)]
+    pub events: Vec<EventSpec>,
+}
+
+/// A constructor of the contract.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConstructorSpec {
+    /// The name of the constructor, e.g. `new`.
+    #[serde(alias = "name", deserialize_with = "deserialize_label")]
+    pub label: String,
+    /// The hex encoded selector.
+    pub selector: String,
+    /// The arguments of the constructor.
+    #[serde(default)]
+    pub args: Vec<ArgSpec>,
+}
+
+/// A message of the contract.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageSpec {
+    /// The name of the message, e.g. `flip`.
+    #[serde(alias = "name", deserialize_with = "deserialize_label")]
+    pub label: String,
+    /// The hex encoded selector.
+    pub selector: String,
+    /// The arguments of the message.
+    #[serde(default)]
+    pub args: Vec<ArgSpec>,
+    /// `true` if the message mutates the contract's storage, it then has to be
+    /// executed as a transaction.
+    #[serde(default)]
+    pub mutates: bool,
+    /// `true` if the message accepts a payment.
+    #[serde(default)]
+    pub payable: bool,
+    /// The type of the returned value, `None` if nothing is returned.
+    pub return_type: Option<TypeSpec>,
+}
+
+/// An argument of a constructor or a message.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ArgSpec {
+    /// The name of the argument, e.g. `init_value`.
+    #[serde(alias = "name", deserialize_with = "deserialize_label")]
+    pub label: String,
+    /// The type of the argument.
+    #[serde(rename = "type")]
+    pub type_spec: TypeSpec,
+}
+
+/// An event which the contract can emit.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EventSpec {
+    /// The name of the event, e.g. `Transfer`.
+    #[serde(alias = "name", deserialize_with = "deserialize_label")]
+    pub label: String,
+    /// The fields of the event.
+    #[serde(default)]
+    pub args: Vec<EventArgSpec>,
+}
+
+/// A field of an event.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EventArgSpec {
+    /// The name of the field, e.g. `from`.
+    #[serde(alias = "name", deserialize_with = "deserialize_label")]
+    pub label: String,
+    /// The type of the field.
+    #[serde(rename = "type")]
+    pub type_spec: TypeSpec,
+}
+
+/// A reference to a type in the type registry.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypeSpec {
+    /// The id of the type in `ContractBundle::types`.
+    #[serde(rename = "type")]
+    pub id: u64,
+    /// The name of the type as written in the contract, e.g. `["Balance"]`.
+    #[serde(default)]
+    pub display_name: Vec<String>,
+}
+
+impl TypeSpec {
+    /// Returns the last segment of the display name, e.g. `AccountId`.
+    pub fn display_name(&self) -> Option<&str> {
+        self.display_name.last().map(String::as_str)
+    }
+}
+
+/// Deserializes a name, which is either a `label` or a path like
+/// `["BaseErc20", "transfer"]` of which the last segment is used.
+fn deserialize_label<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Name {
+        Label(String),
+        Path(Vec<String>),
+    }
+    match Name::deserialize(deserializer)? {
+        Name::Label(label) => Ok(label),
+        Name::Path(path) => {
+            path.last()
+                .cloned()
+                .ok_or_else(|| serde::de::Error::custom("the name must not be empty"))
+        }
+    }
+}
@@ -0,0 +1,256 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Fluent assertions on the `Events` of an extrinsic.
+//!
+//! A failed assertion panics with the expected and the actual events, so that
+//! the test output shows what happened instead of just `false`.
+//!
+//! # Usage
+//!
+//! ```no_compile
+//! events
+//!     .assert()
+//!     .contains("contracts.Terminated")
+//!     .does_not_contain("system.ExtrinsicFailed")
+//!     .count("balances.Transfer", 1)
+//!     .contains_matching(
+//!         EventMatcher::new("balances.Transfer")
+//!             .field("to", FieldValue::Account(alice))
+//!             .field_where("value", |value| value.as_balance() > Some(Balance::default())),
+//!     );
+//! ```
+
+use super::events::{
+    Event,
+    Events,
+    FieldValue,
+};
+use std::fmt;
+
+impl Events {
+    /// Returns the assertions on these events.
+    pub fn assert(&self) -> EventAssertions<'_> {
+        EventAssertions { events: self }
+    }
+}
+
+/// A predicate on an event: its name and conditions on its fields.
+pub struct EventMatcher {
+    name: String,
+    fields: Vec<(String, FieldCondition)>,
+}
+
+/// A condition on the value of a field.
+enum FieldCondition {
+    Equals(FieldValue),
+    Satisfies(Box<dyn Fn(&FieldValue) -> bool>),
+}
+
+impl EventMatcher {
+    /// Matches the events `name`, e.g. `balances.Transfer`.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            fields: Vec::new(),
+        }
+    }
+
+    /// Only matches events whose field `name` is `value`.
+    pub fn field(mut self, name: &str, value: FieldValue) -> Self {
+        self.fields
+            .push((name.to_string(), FieldCondition::Equals(value)));
+        self
+    }
+
+    /// Only matches events whose field `name` satisfies `predicate`.
+    pub fn field_where<P>(mut self, name: &str, predicate: P) -> Self
+    where
+        P: Fn(&FieldValue) -> bool + 'static,
+    {
+        self.fields.push((
+            name.to_string(),
+            FieldCondition::Satisfies(Box::new(predicate)),
+        ));
+        self
+    }
+
+    /// Returns `true` if `event` matches.
+    pub fn matches(&self, event: &Event) -> bool {
+        event.is(&self.name)
+            && self.fields.iter().all(|(name, condition)| {
+                match (event.field(name), condition) {
+                    (Some(value), FieldCondition::Equals(expected)) => value == expected,
+                    (Some(value), FieldCondition::Satisfies(predicate)) => {
+                        predicate(value)
+                    }
+                    (None, _) => false,
+                }
+            })
+    }
+}
+
+impl fmt::Display for EventMatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.fields.is_empty() {
+            let fields = self
+                .fields
+                .iter()
+                .map(|(name, condition)| {
+                    match condition {
+                        FieldCondition::Equals(value) => format!("{}: {}", name, value),
+                        FieldCondition::Satisfies(_) => format!("{}: <predicate>", name),
+                    }
+                })
+                .collect::<Vec<_>>();
+            write!(f, " {{ {} }}", fields.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Assertions on `Events`, each of them panics if it doesn't hold.
+pub struct EventAssertions<'a> {
+    events: &'a Events,
+}
+
+impl<'a> EventAssertions<'a> {
+    /// Asserts that the event `name` (e.g. `balances.Transfer`) was emitted.
+    pub fn contains(self, name: &str) -> Self {
+        if !self.events.contains(name) {
+            self.fail(&format!("expected the event {}", name));
+        }
+        self
+    }
+
+    /// Asserts that an event which matches `matcher` was emitted.
+    pub fn contains_matching(self, matcher: EventMatcher) -> Self {
+        if !self.events.iter().any(|event| matcher.matches(event)) {
+            self.fail(&format!("expected an event matching {}", matcher));
+        }
+        self
+    }
+
+    /// Asserts that the event `name` was not emitted.
+    pub fn does_not_contain(self, name: &str) -> Self {
+        if self.events.contains(name) {
+            self.fail(&format!("expected no event {}", name));
+        }
+        self
+    }
+
+    /// Asserts that the event `name` was emitted exactly `count` times.
+    pub fn count(self, name: &str, count: usize) -> Self {
+        let actual = self.events.iter().filter(|event| event.is(name)).count();
+        if actual != count {
+            self.fail(&format!(
+                "expected the event {} {} time(s), but it was emitted {} time(s)",
+                name, count, actual
+            ));
+        }
+        self
+    }
+
+    /// Asserts that the events `names` were emitted in this order, other events
+    /// may have been emitted in between.
+    pub fn contains_in_order(self, names: &[&str]) -> Self {
+        let mut events = self.events.iter();
+        let in_order = names.iter().all(|name| events.any(|event| event.is(name)));
+        if !in_order {
+            self.fail_with_diff("expected the events in this order", names);
+        }
+        self
+    }
+
+    /// Asserts that exactly the events `names` were emitted, in this order.
+    pub fn contains_exactly(self, names: &[&str]) -> Self {
+        let exactly = self.events.iter().count() == names.len()
+            && self
+                .events
+                .iter()
+                .zip(names)
+                .all(|(event, name)| event.is(name));
+        if !exactly {
+            self.fail_with_diff("expected exactly these events", names);
+        }
+        self
+    }
+
+    /// Panics with `message` and the emitted events.
+    fn fail(&self, message: &str) -> ! {
+        panic!(
+            "{}, but the emitted events are:\n{}",
+            message,
+            indent(self.events)
+        )
+    }
+
+    /// Panics with `message` and a diff between the `expected` events and the
+    /// emitted events.
+    ///
+    /// Lines starting with `-` are expected but were not emitted, lines starting
+    /// with `+` were emitted but not expected.
+    fn fail_with_diff(&self, message: &str, expected: &[&str]) -> ! {
+        let actual: Vec<&Event> = self.events.iter().collect();
+        panic!(
+            "{} (- expected, + emitted):\n{}",
+            message,
+            diff(expected, &actual)
+        )
+    }
+}
+
+/// Returns the events one per line, indented.
+fn indent(events: &Events) -> String {
+    events
+        .iter()
+        .map(|event| format!("    {}\n", event))
+        .collect()
+}
+
+/// Returns a line based diff between the `expected` names and the `actual`
+/// events, computed via their longest common subsequence.
+fn diff(expected: &[&str], actual: &[&Event]) -> String {
+    // lcs[i][j] is the length of the longest common subsequence of
+    // `expected[i..]` and `actual[j..]`
+    let mut lcs = vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            lcs[i][j] = if actual[j].is(expected[i]) {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut lines = String::new();
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && actual[j].is(expected[i]) {
+            lines.push_str(&format!("    {}\n", actual[j]));
+            i += 1;
+            j += 1;
+        } else if j < actual.len()
+            && (i == expected.len() || lcs[i][j + 1] >= lcs[i + 1][j])
+        {
+            lines.push_str(&format!("  + {}\n", actual[j]));
+            j += 1;
+        } else {
+            lines.push_str(&format!("  - {}\n", expected[i]));
+            i += 1;
+        }
+    }
+    lines
+}
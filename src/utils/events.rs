@@ -0,0 +1,502 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The events which are emitted by an extrinsic, with their decoded fields.
+//!
+//! The fields are decoded with the signatures of the events in the runtime
+//! metadata of the `canvas-node`, their names are taken from the documentation of
+//! the events. The fields of types which can't be decoded are left out, the event
+//! can still be found by its name.
+//!
+//! The ink! events which a contract emits are decoded with the `spec.events` of
+//! the contract's metadata. They are named after the contract, e.g. `erc20.Transfer`.
+
+use super::{
+    balance::Balance,
+    contract_bundle::ContractBundle,
+    scale,
+};
+use codec::Decode;
+use once_cell::sync::OnceCell;
+use std::{
+    collections::HashMap,
+    fmt,
+};
+use subxt::{
+    sp_core::crypto::Ss58Codec,
+    sp_runtime::AccountId32,
+    RawEvent,
+    RuntimeError,
+};
+
+/// The signatures of the events by pallet and variant, taken from the runtime
+/// metadata by `set_signatures`.
+static SIGNATURES: OnceCell<HashMap<(String, String), Vec<(String, Option<FieldType>)>>> =
+    OnceCell::new();
+
+/// The type of a field of an event.
+#[derive(Debug, Clone, Copy)]
+enum FieldType {
+    AccountId,
+    Balance,
+    Hash,
+    U32,
+    Bytes,
+}
+
+impl FieldType {
+    /// Returns the type with the name `name` in the runtime metadata, e.g.
+    /// `AccountId`, or `None` if it is unknown.
+    fn from_type_name(name: &str) -> Option<Self> {
+        match name.trim_start_matches("T::") {
+            "AccountId" => Some(FieldType::AccountId),
+            "Balance" | "BalanceOf<T>" => Some(FieldType::Balance),
+            "Hash" | "CodeHash<T>" => Some(FieldType::Hash),
+            "u32" => Some(FieldType::U32),
+            "Vec<u8>" => Some(FieldType::Bytes),
+            _ => None,
+        }
+    }
+}
+
+/// Sets the signatures of the events from `events`, the names and type names of
+/// their fields by pallet and variant as read from the runtime metadata.
+///
+/// All nodes run the same runtime, so the signatures are only set once per test
+/// run.
+pub fn set_signatures(events: &HashMap<(String, String), Vec<(String, String)>>) {
+    SIGNATURES.get_or_init(|| {
+        events
+            .iter()
+            .map(|(event, fields)| {
+                let fields = fields
+                    .iter()
+                    .map(|(name, ty)| (name.clone(), FieldType::from_type_name(ty)))
+                    .collect();
+                (event.clone(), fields)
+            })
+            .collect()
+    });
+}
+
+/// Returns the names and types of the fields of the event `variant` of the
+/// pallet `pallet`, or `None` if the event is unknown.
+fn signature(
+    pallet: &str,
+    variant: &str,
+) -> Option<&'static [(String, Option<FieldType>)]> {
+    SIGNATURES
+        .get()?
+        .get(&(pallet.to_string(), variant.to_string()))
+        .map(Vec::as_slice)
+}
+
+/// The decoded value of a field of an event.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    /// An account, as SS58 address.
+    Account(String),
+    /// A balance of the chain's token.
+    Balance(Balance),
+    /// A hash, hex encoded with a `0x` prefix.
+    Hash(String),
+    /// A number.
+    Number(u64),
+    /// Raw bytes.
+    Bytes(Vec<u8>),
+    /// Any other value of an ink! event, formatted as text.
+    Text(String),
+    /// An `Option` of an ink! event which is `None`. The value of a `Some` is
+    /// decoded as the value itself.
+    None,
+}
+
+impl FieldValue {
+    /// Returns the SS58 address if this is an account.
+    pub fn as_account(&self) -> Option<&str> {
+        match self {
+            FieldValue::Account(account) => Some(account),
+            _ => None,
+        }
+    }
+
+    /// Returns the balance if this is a balance.
+    pub fn as_balance(&self) -> Option<Balance> {
+        match self {
+            FieldValue::Balance(balance) => Some(*balance),
+            _ => None,
+        }
+    }
+
+    /// Decodes a value of type `ty` from `input`.
+    fn decode(ty: FieldType, input: &mut &[u8]) -> Result<Self, codec::Error> {
+        let value = match ty {
+            FieldType::AccountId => {
+                let account = AccountId32::from(<[u8; 32]>::decode(input)?);
+                FieldValue::Account(account.to_ss58check())
+            }
+            FieldType::Balance => {
+                FieldValue::Balance(Balance::from_plancks(u128::decode(input)?))
+            }
+            FieldType::Hash => {
+                FieldValue::Hash(format!("0x{}", hex::encode(<[u8; 32]>::decode(input)?)))
+            }
+            FieldType::U32 => FieldValue::Number(u32::decode(input)?.into()),
+            FieldType::Bytes => FieldValue::Bytes(Vec::<u8>::decode(input)?),
+        };
+        Ok(value)
+    }
+
+    /// Decodes a value of the type `id` of the contract's type registry `types`.
+    ///
+    /// `display_name` is the name of the type as written in the contract, it tells
+    /// e.g. a `Balance` apart from other `u128`s.
+    fn decode_ink(
+        types: &[serde_json::Value],
+        id: u64,
+        display_name: Option<&str>,
+        input: &mut &[u8],
+    ) -> Result<Self, String> {
+        let ty = match display_name {
+            Some("Balance") => Some(FieldType::Balance),
+            Some("Hash") => Some(FieldType::Hash),
+            _ => None,
+        };
+        let ty = ty.or_else(|| {
+            match scale::type_name(types, id) {
+                Some("AccountId") => Some(FieldType::AccountId),
+                Some("Hash") => Some(FieldType::Hash),
+                _ => None,
+            }
+        });
+        if let Some(ty) = ty {
+            return Self::decode(ty, input).map_err(|err| err.to_string())
+        }
+        if scale::type_name(types, id) == Some("Option") {
+            return match u8::decode(input).map_err(|err| err.to_string())? {
+                0 => Ok(FieldValue::None),
+                _ => {
+                    Self::decode_ink(types, scale::option_param(types, id)?, None, input)
+                }
+            }
+        }
+        scale::decode(types, id, input).map(FieldValue::Text)
+    }
+}
+
+impl fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldValue::Account(account) => write!(f, "{}", account),
+            FieldValue::Balance(balance) => write!(f, "{}", balance),
+            FieldValue::Hash(hash) => write!(f, "{}", hash),
+            FieldValue::Number(number) => write!(f, "{}", number),
+            FieldValue::Bytes(bytes) => write!(f, "0x{}", hex::encode(bytes)),
+            FieldValue::Text(text) => write!(f, "{}", text),
+            FieldValue::None => write!(f, "None"),
+        }
+    }
+}
+
+/// An event emitted by an extrinsic, e.g. `balances.Transfer`.
+#[derive(Debug, Clone)]
+pub struct Event {
+    /// The pallet which emitted the event, as the UI displays it, e.g. `balances`.
+    pub pallet: String,
+    /// The name of the event within the pallet, e.g. `Transfer`.
+    pub variant: String,
+    /// The decoded fields of the event, e.g. `from`, `to` and `amount`.
+    pub fields: Vec<(String, FieldValue)>,
+    /// The status text which the UI displays for the event, e.g.
+    /// `contracts.NotCallable` for `system.ExtrinsicFailed`.
+    pub status: String,
+}
+
+impl Event {
+    /// Creates an event from a notification of the UI with the `header` (e.g.
+    /// `balances.Transfer`) and `status`. Its fields are unknown.
+    pub fn from_notification(header: &str, status: &str) -> Self {
+        let (pallet, variant) = match header.find('.') {
+            Some(dot) => (&header[..dot], &header[dot + 1..]),
+            None => ("", header),
+        };
+        Self {
+            pallet: pallet.to_string(),
+            variant: variant.to_string(),
+            fields: Vec::new(),
+            status: status.to_string(),
+        }
+    }
+
+    /// Creates the `system.ExtrinsicFailed` event for the dispatch error `err`.
+    ///
+    /// The events decoder of `subxt` returns the dispatch error instead of this
+    /// event. Like in the UI, the status of the event is the error, e.g.
+    /// `contracts.NotCallable`.
+    pub fn extrinsic_failed(err: &RuntimeError) -> Self {
+        let status = match err {
+            RuntimeError::Module(err) => {
+                format!("{}.{}", section(&err.module), err.error)
+            }
+            err => format!("{:?}", err),
+        };
+        Self::from_notification("system.ExtrinsicFailed", &status)
+    }
+
+    /// Creates an event from an event read from the chain and decodes its fields.
+    pub fn decode(raw: &RawEvent) -> Self {
+        let pallet = section(&raw.module);
+        let mut fields = Vec::new();
+        if let Some(signature) = signature(&pallet, &raw.variant) {
+            let input = &mut &raw.data[..];
+            for (name, ty) in signature {
+                // the size of a value of an unknown type is unknown as well, so
+                // the fields after it can't be decoded either
+                let ty = match ty {
+                    Some(ty) => *ty,
+                    None => break,
+                };
+                match FieldValue::decode(ty, input) {
+                    Ok(value) => fields.push((name.to_string(), value)),
+                    Err(err) => {
+                        log::warn!(
+                            "unable to decode the field {:?} of {}.{}: {:?}",
+                            name,
+                            pallet,
+                            raw.variant,
+                            err
+                        );
+                        break
+                    }
+                }
+            }
+        }
+        Self {
+            pallet,
+            variant: raw.variant.clone(),
+            fields,
+            status: "event".to_string(),
+        }
+    }
+
+    /// Decodes the ink! event in the `data` of a `contracts.ContractEmitted` event
+    /// with the metadata of the emitting contract.
+    ///
+    /// The event is encoded as the index of the event in `spec.events`, followed
+    /// by its fields.
+    pub fn decode_contract_event(
+        bundle: &ContractBundle,
+        data: &[u8],
+    ) -> Result<Self, String> {
+        let input = &mut &data[..];
+        let index = u8::decode(input).map_err(|err| err.to_string())?;
+        let spec = bundle.spec.events.get(index as usize).ok_or_else(|| {
+            format!(
+                "the contract `{}` has no event with index {}",
+                bundle.contract.name, index
+            )
+        })?;
+        let mut fields = Vec::new();
+        for arg in &spec.args {
+            let value = FieldValue::decode_ink(
+                &bundle.types,
+                arg.type_spec.id,
+                arg.type_spec.display_name(),
+                input,
+            )
+            .map_err(|err| {
+                format!(
+                    "unable to decode the field {:?} of {}: {}",
+                    arg.label, spec.label, err
+                )
+            })?;
+            fields.push((arg.label.clone(), value));
+        }
+        Ok(Self {
+            pallet: bundle.contract.name.clone(),
+            variant: spec.label.clone(),
+            fields,
+            status: "event".to_string(),
+        })
+    }
+
+    /// Returns the name of the event, e.g. `balances.Transfer`.
+    pub fn name(&self) -> String {
+        format!("{}.{}", self.pallet, self.variant)
+    }
+
+    /// Returns `true` if this is the event `name` (e.g. `balances.Transfer`) or
+    /// if the UI displays `name` as its status.
+    pub fn is(&self, name: &str) -> bool {
+        self.name() == name || self.status == name
+    }
+
+    /// Returns the value of the field `name`.
+    pub fn field(&self, name: &str) -> Option<&FieldValue> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value)
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())?;
+        if !self.fields.is_empty() {
+            let fields = self
+                .fields
+                .iter()
+                .map(|(name, value)| format!("{}: {}", name, value))
+                .collect::<Vec<_>>();
+            write!(f, " {{ {} }}", fields.join(", "))?;
+        }
+        if self.status != "event" {
+            write!(f, " ({})", self.status)?;
+        }
+        Ok(())
+    }
+}
+
+/// The events emitted by an extrinsic, in the order in which they were emitted.
+#[derive(Debug)]
+pub struct Events {
+    events: Vec<Event>,
+}
+
+impl Events {
+    /// Creates a new `Events` instance.
+    pub fn new(events: Vec<Event>) -> Self {
+        Self { events }
+    }
+
+    /// Returns `true` if the `event` is contained in these events.
+    pub fn contains(&self, event: &str) -> bool {
+        self.iter().any(|evt| evt.is(event))
+    }
+
+    /// Returns the first event `name`, e.g. `balances.Transfer`.
+    pub fn find(&self, name: &str) -> Option<&Event> {
+        self.iter().find(|event| event.is(name))
+    }
+
+    /// Returns the error with which the extrinsic failed, taken from the status of
+    /// its `system.ExtrinsicFailed` event.
+    ///
+    /// The documentation of the error is not part of the status, it is empty.
+    pub fn dispatch_error(&self) -> Option<DispatchError> {
+        self.find("system.ExtrinsicFailed")
+            .map(|event| DispatchError::parse(&event.status))
+    }
+
+    /// Decodes the ink! events of the `contracts.ContractEmitted` events, with the
+    /// metadata of the emitting contracts in `bundles` (by address).
+    ///
+    /// Each decoded event is inserted after its `contracts.ContractEmitted`
+    /// event. Events of contracts with unknown metadata are skipped.
+    pub fn decode_contract_events(&mut self, bundles: &HashMap<String, ContractBundle>) {
+        let mut events = Vec::with_capacity(self.events.len());
+        for event in self.events.drain(..) {
+            let contract_event = match (event.field("contract"), event.field("data")) {
+                (Some(FieldValue::Account(contract)), Some(FieldValue::Bytes(data)))
+                    if event.is("contracts.ContractEmitted") =>
+                {
+                    match bundles.get(contract) {
+                        Some(bundle) => {
+                            Event::decode_contract_event(bundle, data)
+                                .map_err(|err| {
+                                    log::warn!("unable to decode the ink! event: {}", err)
+                                })
+                                .ok()
+                        }
+                        None => {
+                            log::info!(
+                                "the metadata of {:?} is unknown, its event can't be decoded",
+                                contract
+                            );
+                            None
+                        }
+                    }
+                }
+                _ => None,
+            };
+            events.push(event);
+            events.extend(contract_event);
+        }
+        self.events = events;
+    }
+
+    /// Returns an iterator over the events.
+    pub fn iter(&self) -> impl Iterator<Item = &Event> {
+        self.events.iter()
+    }
+}
+
+impl fmt::Display for Events {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for event in &self.events {
+            writeln!(f, "{}", event)?;
+        }
+        Ok(())
+    }
+}
+
+/// The error with which the dispatch of an extrinsic failed, e.g.
+/// `contracts.NotCallable`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DispatchError {
+    /// The section of the pallet in which the error occurred, e.g. `contracts`.
+    pub module: String,
+    /// The name of the error, e.g. `NotCallable`.
+    pub error: String,
+    /// The documentation of the error in the metadata of the runtime, empty if it
+    /// is unknown.
+    pub docs: String,
+}
+
+impl DispatchError {
+    /// Parses the error from the way the UI displays it, e.g. `contracts.NotCallable`.
+    ///
+    /// If there is no module, the whole of `status` is taken as the error name.
+    pub fn parse(status: &str) -> Self {
+        let (module, error) = match status.find('.') {
+            Some(dot) => (&status[..dot], &status[dot + 1..]),
+            None => ("", status),
+        };
+        Self {
+            module: module.to_string(),
+            error: error.to_string(),
+            docs: String::new(),
+        }
+    }
+}
+
+impl fmt::Display for DispatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.module.is_empty() {
+            write!(f, "{}.", self.module)?;
+        }
+        write!(f, "{}", self.error)
+    }
+}
+
+/// Returns the `section` of a pallet the way the UI displays it, e.g. `balances`
+/// for `Balances`.
+pub fn section(module: &str) -> String {
+    let mut chars = module.chars();
+    match chars.next() {
+        Some(first) => first.to_lowercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
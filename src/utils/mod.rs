@@ -12,14 +12,30 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod account;
+pub mod artifacts;
+pub mod backend;
+pub mod balance;
+pub mod build_cache;
+pub mod canvas_node;
 pub mod canvas_ui;
 pub mod cargo_contract;
+pub mod chain;
+pub mod contract_bundle;
+pub mod event_assertions;
+pub mod events;
+pub mod node_rpc;
+pub mod pages;
+pub mod scale;
+pub mod selectors;
+pub mod ui_server;
+pub mod validation;
+pub mod value;
+pub mod wait;
 
-use serde_json;
 use std::{
-    fs::File,
-    io::BufReader,
     path::PathBuf,
+    time::Duration,
 };
 
 /// Returns the full path to the ink! example directory for `example`.
@@ -30,18 +46,22 @@ pub fn example_path(example: &str) -> PathBuf {
     path.join(example)
 }
 
-/// Extracts the `source.hash` field from the contract bundle.
-pub fn extract_hash_from_contract_bundle(path: &PathBuf) -> String {
-    let file =
-        File::open(path).expect(&format!("Contract file at {:?} does not exist", path));
-    let reader = BufReader::new(file);
-    let json: serde_json::Value =
-        serde_json::from_reader(reader).expect("JSON is not well-formatted");
-    json.get("source")
-        .expect("Unable to get 'source' field from contract JSON")
-        .get("hash")
-        .expect("Unable to get 'hash' field from contract JSON")
-        .to_string()
-        .trim_matches('"')
-        .to_string()
+/// Returns the full path to the file `fixture` in the `fixtures` directory.
+pub fn fixture_path(fixture: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("fixtures")
+        .join(fixture)
+}
+
+/// Returns the time each test is allowed to take.
+///
+/// Defaults to five minutes, can be overridden with the environment variable
+/// `WATERFALL_TIMEOUT_SECS_PER_TEST`.
+pub fn timeout_from_env() -> Duration {
+    let timeout: String =
+        std::env::var("WATERFALL_TIMEOUT_SECS_PER_TEST").unwrap_or(String::from("300")); // 5 * 60 = five minutes
+    let timeout: u64 = timeout
+        .parse::<u64>()
+        .expect("unable to parse WATERFALL_TIMEOUT_SECS_PER_TEST into u64");
+    Duration::from_secs(timeout)
 }
@@ -0,0 +1,480 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A backend which talks to the `canvas-node` directly, without a browser.
+//!
+//! It is used to find out whether a failing UI test is caused by the chain or by
+//! the UI, and as a fast smoke test mode.
+
+use super::{
+    account::Account,
+    backend::{
+        Call,
+        ContractsBackend,
+        Error,
+        Event,
+        Events,
+        RpcOutcome,
+        Upload,
+    },
+    balance::{
+        AccountBalance,
+        Balance,
+    },
+    chain::Chain,
+    contract_bundle::{
+        ArgSpec,
+        ContractBundle,
+    },
+    scale,
+    validation::{
+        self,
+        normalize,
+        CallKind,
+    },
+    value::Value as Decoded,
+};
+use async_trait::async_trait;
+use std::{
+    collections::HashMap,
+    path::Path,
+    time::{
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
+use subxt::{
+    balances::TransferCallExt,
+    contracts::*,
+    sp_core::{
+        crypto::Ss58Codec,
+        sr25519,
+        H256,
+    },
+    sp_runtime::{
+        AccountId32,
+        MultiAddress,
+    },
+    Client,
+    ClientBuilder,
+    ContractsTemplateRuntime,
+    ExtrinsicSuccess,
+    PairSigner,
+};
+
+/// The gas limit used for calls which don't specify one.
+const DEFAULT_GAS_LIMIT: u64 = 50_000_000_000;
+
+/// The `canvas-ui` takes the max gas in units of one million.
+const GAS_UNIT: u64 = 1_000_000;
+
+type Signer = PairSigner<ContractsTemplateRuntime, sr25519::Pair>;
+
+/// Holds everything necessary to interact with the `canvas-node` directly.
+pub struct NodeRpc {
+    client: Client<ContractsTemplateRuntime>,
+    /// A second connection to the node, to read the metadata of the runtime.
+    chain: Chain,
+    /// The bundles of the contracts instantiated by this backend, by address.
+    contracts: HashMap<String, ContractBundle>,
+}
+
+impl NodeRpc {
+    /// Creates a new `NodeRpc` instance connected to the node at `node_url`.
+    pub async fn new(node_url: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        log::info!("connecting to the canvas-node at {:?}", node_url);
+        let client = ClientBuilder::<ContractsTemplateRuntime>::new()
+            .set_url(node_url)
+            .build()
+            .await?;
+        Ok(Self {
+            client,
+            chain: Chain::connect(node_url).await?,
+            contracts: HashMap::new(),
+        })
+    }
+
+    /// Returns the bundle of the contract at `address`.
+    fn bundle(&self, address: &str) -> Result<&ContractBundle, String> {
+        self.contracts.get(address).ok_or_else(|| {
+            format!(
+                "the contract {} was not instantiated by this backend, its metadata is unknown",
+                address
+            )
+        })
+    }
+
+    /// Returns the address of the contract which the instantiation `result`
+    /// created, and remembers that `bundle` describes it.
+    async fn instantiated(
+        &mut self,
+        result: Result<ExtrinsicSuccess<ContractsTemplateRuntime>, subxt::Error>,
+        bundle: ContractBundle,
+    ) -> Result<String, Error> {
+        let result = match result {
+            Ok(result) => result,
+            Err(err) => return Err(self.extrinsic_error(err).await),
+        };
+        let instantiated = result
+            .instantiated()
+            .map_err(|err| Error::Other(Box::new(err)))?
+            .ok_or_else(|| {
+                Error::Other("no `contracts.Instantiated` event was emitted".into())
+            })?;
+        let addr = instantiated.contract.to_ss58check();
+        log::info!("contract address {:?}", addr);
+        self.contracts.insert(addr.clone(), bundle);
+        Ok(addr)
+    }
+
+    /// Converts the error with which an extrinsic failed into the `Error` of the
+    /// backend.
+    async fn extrinsic_error(&self, err: subxt::Error) -> Error {
+        match err {
+            subxt::Error::Runtime(err) => {
+                // this mirrors the notification which the `canvas-ui` shows
+                let failed = Event::extrinsic_failed(&err);
+                log::info!("extrinsic failed with {:?}", failed);
+                let events = Events::new(vec![failed]);
+                let dispatch_error = self.chain.dispatch_error(&events).await;
+                Error::extrinsic_failed(dispatch_error, events)
+            }
+            err => Error::Other(Box::new(err)),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl ContractsBackend for NodeRpc {
+    async fn shutdown(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    async fn balance(&mut self, account: &Account) -> Result<AccountBalance, Error> {
+        self.chain
+            .account_balance(&account.account_id())
+            .await
+            .map_err(Error::Other)
+    }
+
+    async fn create_account(&mut self, name: &str, suri: &str) -> Result<Account, Error> {
+        Account::from_suri(name, suri).map_err(|err| Error::Other(err.into()))
+    }
+
+    /// Fails with `Error::Unsupported`, the key pair is encrypted in the keystore
+    /// and only the UI is able to decrypt it.
+    async fn import_account(
+        &mut self,
+        path: &Path,
+        _password: &str,
+    ) -> Result<Account, Error> {
+        Err(Error::Unsupported(format!(
+            "the key pair in the keystore {:?} can't be decrypted without the UI",
+            path
+        )))
+    }
+
+    async fn fund_account(
+        &mut self,
+        account: &Account,
+        from: &Account,
+        amount: Balance,
+    ) -> Result<Events, Error> {
+        let signer = signer(Some(from)).map_err(Error::InvalidCall)?;
+        log::info!("transferring {} from {:?} to {:?}", amount, from, account);
+        let result = self
+            .client
+            .transfer_and_watch(
+                &signer,
+                &MultiAddress::Id(account.account_id()),
+                amount.plancks(),
+            )
+            .await;
+        match result {
+            Ok(success) => {
+                Ok(Events::new(
+                    success.events.iter().map(Event::decode).collect(),
+                ))
+            }
+            Err(err) => Err(self.extrinsic_error(err).await),
+        }
+    }
+
+    async fn execute_upload(&mut self, upload: Upload) -> Result<String, Error> {
+        log::info!("reading contract bundle {:?}", upload.contract_path);
+        let bundle = ContractBundle::from_path(&upload.contract_path)?;
+        validation::check_upload(&bundle, &upload).map_err(Error::UploadRejected)?;
+        let code = bundle.wasm()?;
+        let data = constructor_input(&bundle, &upload)?;
+        let signer = signer(upload.caller.as_ref()).map_err(Error::UploadRejected)?;
+
+        log::info!("instantiating {:?}", upload.contract_path);
+        let result = self
+            .client
+            .instantiate_with_code_and_watch(
+                &signer,
+                upload.endowment.plancks(),
+                DEFAULT_GAS_LIMIT,
+                &code,
+                &data,
+                &unique_salt(),
+            )
+            .await;
+        self.instantiated(result, bundle).await
+    }
+
+    async fn execute_instantiate(
+        &mut self,
+        code_hash: &str,
+        upload: Upload,
+    ) -> Result<String, Error> {
+        log::info!("reading contract bundle {:?}", upload.contract_path);
+        let bundle = ContractBundle::from_path(&upload.contract_path)?;
+        validation::check_upload(&bundle, &upload).map_err(Error::UploadRejected)?;
+        let hash = scale::decode_hex(code_hash).map_err(Error::UploadRejected)?;
+        if hash.len() != 32 {
+            return Err(Error::UploadRejected(format!(
+                "{:?} is not a code hash",
+                code_hash
+            )))
+        }
+        let data = constructor_input(&bundle, &upload)?;
+        let signer = signer(upload.caller.as_ref()).map_err(Error::UploadRejected)?;
+
+        log::info!("instantiating the code {}", code_hash);
+        let result = self
+            .client
+            .instantiate_and_watch(
+                &signer,
+                upload.endowment.plancks(),
+                DEFAULT_GAS_LIMIT,
+                &H256::from_slice(&hash),
+                &data,
+                &unique_salt(),
+            )
+            .await;
+        self.instantiated(result, bundle).await
+    }
+
+    async fn execute_code_upload(&mut self, upload: Upload) -> Result<String, Error> {
+        let code_hash = ContractBundle::from_path(&upload.contract_path)?
+            .code_hash()
+            .to_string();
+        log::info!(
+            "storing the code {} of {:?}",
+            code_hash,
+            upload.contract_path
+        );
+        let addr = self.execute_upload(upload.default_values()).await?;
+        log::info!(
+            "the code {} was stored with the contract {}",
+            code_hash,
+            addr
+        );
+        Ok(code_hash)
+    }
+
+    async fn execute_rpc(&mut self, call: Call) -> Result<RpcOutcome, Error> {
+        let bundle = self
+            .bundle(&call.contract_address)
+            .map_err(Error::InvalidCall)?;
+        let value = dry_run(&self.chain, bundle, &call).await?;
+        let outcome = RpcOutcome::from_value(value);
+        log::info!("rpc outcome {:?}", outcome.text());
+        Ok(outcome)
+    }
+
+    async fn execute_transaction(&mut self, call: Call) -> Result<Events, Error> {
+        let bundle = self
+            .bundle(&call.contract_address)
+            .map_err(Error::InvalidCall)?;
+        let (data, _) = encode_call(bundle, &call, CallKind::Transaction)
+            .map_err(Error::InvalidCall)?;
+        let value = call.payment.map(Balance::plancks).unwrap_or_default();
+        let gas_limit = gas_limit(&call).map_err(Error::InvalidCall)?;
+        let signer = signer(call.caller.as_ref()).map_err(Error::InvalidCall)?;
+        let dest = AccountId32::from_ss58check(&call.contract_address)
+            .map_err(|err| Error::InvalidCall(format!("{:?}", err)))?;
+
+        log::info!("submitting transaction for {:?}", call.method);
+        let result = self
+            .client
+            .call_and_watch(&signer, &MultiAddress::Id(dest), value, gas_limit, &data)
+            .await;
+        let success = match result {
+            Ok(success) => success,
+            Err(err) => return Err(self.extrinsic_error(err).await),
+        };
+        let events = success.events.iter().map(Event::decode).collect();
+        let mut events = Events::new(events);
+        events.decode_contract_events(&self.contracts);
+        Ok(events)
+    }
+}
+
+/// Executes the RPC call `call` of the contract `bundle` on the node and returns
+/// its return value, decoded with the return type of the message. Messages
+/// without a return type return `None`.
+///
+/// The `canvas-ui` backend uses this as well, to cross-check the return value
+/// which the UI displays and to get the full value of long ones, which the UI
+/// shortens.
+pub async fn dry_run(
+    chain: &Chain,
+    bundle: &ContractBundle,
+    call: &Call,
+) -> Result<Option<Decoded>, Error> {
+    let (data, return_type) =
+        encode_call(bundle, call, CallKind::Rpc).map_err(Error::InvalidCall)?;
+    let origin = caller(call.caller.as_ref()).address();
+    let gas_limit = gas_limit(call).map_err(Error::InvalidCall)?;
+    log::info!("sending `contracts_call` for {:?}", call.method);
+    let output = chain
+        .contracts_call(&origin, &call.contract_address, gas_limit, &data)
+        .await
+        .map_err(Error::Other)?;
+    return_type
+        .map(|id| {
+            scale::decode_value(&bundle.types, id, &mut &output[..])
+                .map_err(|err| Error::Other(err.into()))
+        })
+        .transpose()
+}
+
+/// Encodes the constructor of `upload` together with its initial values.
+fn constructor_input(bundle: &ContractBundle, upload: &Upload) -> Result<Vec<u8>, Error> {
+    let constructor = bundle
+        .constructor(upload.constructor.as_deref())
+        .ok_or_else(|| {
+            Error::UploadRejected(format!(
+                "{:?} not found in `spec.constructors`",
+                upload.constructor
+            ))
+        })?;
+    encode_input(
+        bundle,
+        &constructor.selector,
+        &constructor.args,
+        &upload.initial_values,
+    )
+    .map_err(Error::UploadRejected)
+}
+
+/// Returns the encoded input data and the return type for a call of `call.method`
+/// of the contract `bundle`, which is executed as `kind`.
+fn encode_call(
+    bundle: &ContractBundle,
+    call: &Call,
+    kind: CallKind,
+) -> Result<(Vec<u8>, Option<u64>), String> {
+    validation::check_call(bundle, call, kind)?;
+    let message = bundle
+        .message(&call.method)
+        .ok_or_else(|| format!("{:?} not found in `spec.messages`", call.method))?;
+    let data = encode_input(bundle, &message.selector, &message.args, &call.values)?;
+    let return_type = message.return_type.as_ref().map(|ty| ty.id);
+    Ok((data, return_type))
+}
+
+/// Encodes the `selector` followed by the `args` taken from `values`.
+///
+/// The keys of `values` are matched the way the UI labels the inputs, i.e.
+/// `initialSupply` and `by: i32` match the arguments `initial_supply` and `by`.
+/// Missing arguments are encoded as their default value, the validation only
+/// lets them through if the upload opted into `default_values`.
+fn encode_input(
+    bundle: &ContractBundle,
+    selector: &str,
+    args: &[ArgSpec],
+    values: &[(String, String)],
+) -> Result<Vec<u8>, String> {
+    let mut data = scale::decode_hex(selector)?;
+
+    let types = &bundle.types;
+    for arg in args {
+        let id = arg.type_spec.id;
+        let value = values
+            .iter()
+            .find(|(key, _)| normalize(key) == normalize(&arg.label))
+            .map(|(_, value)| value);
+        match (value, arg.type_spec.display_name()) {
+            (None, _) => scale::encode_default(types, id, &mut data)?,
+            (Some(value), Some("AccountId")) => {
+                let account = account(value)?;
+                let account: &[u8] = account.as_ref();
+                let hex = format!("0x{}", hex::encode(account));
+                scale::encode(types, id, &hex, &mut data)?
+            }
+            (Some(value), Some("Balance")) => {
+                // the UI takes balances in units of the chain's token
+                let plancks = Balance::parse(value)?.plancks();
+                scale::encode(types, id, &plancks.to_string(), &mut data)?
+            }
+            (Some(value), _) => scale::encode(types, id, value, &mut data)?,
+        }
+    }
+    Ok(data)
+}
+
+/// Returns the gas limit for `call`.
+fn gas_limit(call: &Call) -> Result<u64, String> {
+    match &call.max_gas_allowed {
+        Some(max_gas) => {
+            let max_gas = max_gas
+                .parse::<u64>()
+                .map_err(|err| format!("invalid max gas {:?}: {:?}", max_gas, err))?;
+            Ok(max_gas * GAS_UNIT)
+        }
+        None => Ok(DEFAULT_GAS_LIMIT),
+    }
+}
+
+/// Returns the account for `name`, which is either the name of a dev account
+/// or an SS58 address.
+fn account(name: &str) -> Result<AccountId32, String> {
+    match Account::from_name(name) {
+        Some(account) => Ok(account.account_id()),
+        None => {
+            AccountId32::from_ss58check(name)
+                .map_err(|err| format!("unknown account {:?}: {:?}", name, err))
+        }
+    }
+}
+
+/// Returns `caller`, or Alice who is preselected in the `canvas-ui`.
+fn caller(caller: Option<&Account>) -> Account {
+    caller.cloned().unwrap_or_else(Account::alice)
+}
+
+/// Returns the signer for `caller`, see [`caller`].
+///
+/// The key pairs of accounts imported from a keystore are only known to the UI.
+fn signer(caller: Option<&Account>) -> Result<Signer, String> {
+    let caller = self::caller(caller);
+    caller
+        .pair()
+        .map(|pair| PairSigner::new(pair.clone()))
+        .ok_or_else(|| format!("the key pair of {:?} is unknown", caller))
+}
+
+/// Returns a salt which differs between instantiations, this corresponds to
+/// the "Unique Instantiation Salt" checkbox in the UI.
+fn unique_salt() -> Vec<u8> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time is before the unix epoch")
+        .as_nanos()
+        .to_le_bytes()
+        .to_vec()
+}
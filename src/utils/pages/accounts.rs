@@ -0,0 +1,228 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The page of the `canvas-ui` on which accounts are added and funded.
+
+use super::{
+    find,
+    goto,
+    set_input,
+    sign_and_submit,
+};
+use crate::utils::{
+    backend::Error,
+    selectors::Selectors,
+    wait::Wait,
+};
+use fantoccini::Client;
+use std::{
+    path::Path,
+    sync::Arc,
+};
+
+/// The page at `#/accounts`.
+pub struct AccountsPage {
+    client: Client,
+    selectors: Arc<Selectors>,
+}
+
+impl AccountsPage {
+    /// Opens the page at `url`.
+    pub async fn open(
+        client: &Client,
+        selectors: &Arc<Selectors>,
+        url: &str,
+    ) -> Result<Self, Error> {
+        let mut client = client.clone();
+        log::info!("opening {:?}", url);
+        goto(&mut client, url).await?;
+        Ok(Self {
+            client,
+            selectors: selectors.clone(),
+        })
+    }
+
+    /// Adds the account `name` for `suri`, a mnemonic phrase or a hex encoded
+    /// seed, and stores it with `password`.
+    pub async fn add_account(
+        &mut self,
+        name: &str,
+        suri: &str,
+        password: &str,
+    ) -> Result<(), Error> {
+        let selectors = self.selectors.clone();
+        let selectors = &selectors.accounts;
+        log::info!("click add account");
+        self.client
+            .wait_for_find(selectors.add_account.locator())
+            .await?
+            .click()
+            .await?;
+
+        let seed_type = if suri.starts_with("0x") {
+            "Raw seed"
+        } else {
+            "Mnemonic"
+        };
+        log::info!("choose {:?}", seed_type);
+        find(&mut self.client, &selectors.seed_type_listbox)
+            .await?
+            .click()
+            .await?;
+        find(
+            &mut self.client,
+            &selectors.seed_type_option.with(seed_type),
+        )
+        .await?
+        .click()
+        .await?;
+        log::info!("entering the secret");
+        set_input(&mut self.client, &selectors.seed_input, suri).await?;
+
+        // only some kinds of secrets have to be confirmed
+        if let Ok(checkbox) = self
+            .client
+            .find(selectors.seed_saved_checkbox.locator())
+            .await
+        {
+            checkbox.click().await?;
+        }
+        self.next().await?;
+
+        log::info!("entering name {:?} and password", name);
+        set_input(&mut self.client, &selectors.name_input, name).await?;
+        set_input(&mut self.client, &selectors.password_input, password).await?;
+        set_input(&mut self.client, &selectors.password_repeat_input, password).await?;
+        self.next().await?;
+
+        log::info!("click save");
+        self.client
+            .wait_for_find(selectors.save.locator())
+            .await?
+            .click()
+            .await?;
+        self.wait_for_account(name).await
+    }
+
+    /// Restores the account `name` from the JSON keystore at `path`, which is
+    /// encrypted with `password`.
+    pub async fn restore_json(
+        &mut self,
+        name: &str,
+        path: &Path,
+        password: &str,
+    ) -> Result<(), Error> {
+        let selectors = &self.selectors.accounts;
+        log::info!("click restore json");
+        self.client
+            .wait_for_find(selectors.restore_json.locator())
+            .await?
+            .click()
+            .await?;
+
+        log::info!("uploading {:?}", path);
+        find(&mut self.client, &selectors.keystore_input)
+            .await?
+            .send_keys(&path.display().to_string())
+            .await?;
+        set_input(
+            &mut self.client,
+            &selectors.restore_password_input,
+            password,
+        )
+        .await?;
+
+        log::info!("click restore");
+        find(&mut self.client, &selectors.restore)
+            .await?
+            .click()
+            .await?;
+        self.wait_for_account(name).await
+    }
+
+    /// Opens the transfer modal of the account `from` and sends `amount` in
+    /// `unit` to the SS58 address `to`.
+    ///
+    /// This stops at the signing modal, see `sign_and_submit`.
+    pub async fn transfer(
+        &mut self,
+        from: &str,
+        to: &str,
+        amount: &str,
+        unit: &str,
+    ) -> Result<(), Error> {
+        let selectors = &self.selectors.accounts;
+        log::info!("click send of {:?}", from);
+        self.client
+            .wait_for_find(selectors.send.with(from).locator())
+            .await?
+            .click()
+            .await?;
+
+        log::info!("entering recipient {:?}", to);
+        set_input(
+            &mut self.client,
+            &selectors.recipient_input,
+            &format!("{}\n", to),
+        )
+        .await?;
+
+        log::info!("click amount unit option {}", unit);
+        find(&mut self.client, &selectors.amount_unit_listbox.with(unit))
+            .await?
+            .click()
+            .await?;
+        self.client
+            .wait_for_find(selectors.amount_unit_option.with(unit).locator())
+            .await?
+            .click()
+            .await?;
+        log::info!("entering amount {:?}", amount);
+        set_input(&mut self.client, &selectors.amount_input, amount).await?;
+
+        log::info!("click make transfer");
+        find(&mut self.client, &selectors.make_transfer)
+            .await?
+            .click()
+            .await?;
+        Ok(())
+    }
+
+    /// Submits the transfer in the signing modal, the dev accounts which fund
+    /// other accounts have no password.
+    pub async fn sign_and_submit(&mut self) -> Result<(), Error> {
+        sign_and_submit(&mut self.client, &self.selectors, None).await
+    }
+
+    /// Clicks the button to the next step of the modal.
+    async fn next(&mut self) -> Result<(), Error> {
+        log::info!("click next");
+        find(&mut self.client, &self.selectors.accounts.next)
+            .await?
+            .click()
+            .await?;
+        Ok(())
+    }
+
+    /// Waits until the account `name` is listed.
+    async fn wait_for_account(&mut self, name: &str) -> Result<(), Error> {
+        Wait::new(&format!("the account {:?}", name))
+            .until_visible(
+                &self.client,
+                self.selectors.accounts.account_row.with(name).locator(),
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The page of the `canvas-ui` on which the messages of a contract are executed.
+
+use super::{
+    find,
+    goto,
+    select_account,
+    set_input,
+    sign_and_submit,
+};
+use crate::utils::{
+    backend::Error,
+    selectors::{
+        Selector,
+        Selectors,
+    },
+};
+use fantoccini::Client;
+use std::sync::Arc;
+
+/// The page at `#/execute/<address>/0`.
+pub struct ExecutePage {
+    client: Client,
+    selectors: Arc<Selectors>,
+}
+
+impl ExecutePage {
+    /// Opens the page at `url`.
+    pub async fn open(
+        client: &Client,
+        selectors: &Arc<Selectors>,
+        url: &str,
+    ) -> Result<Self, Error> {
+        let mut client = client.clone();
+        log::info!("opening {:?}", url);
+        goto(&mut client, url).await?;
+        Ok(Self {
+            client,
+            selectors: selectors.clone(),
+        })
+    }
+
+    /// Reloads the page, which resets the form.
+    pub async fn refresh(&mut self) -> Result<(), Error> {
+        self.client.refresh().await?;
+        Ok(())
+    }
+
+    /// Chooses the message `method`.
+    pub async fn select_message(&mut self, method: &str) -> Result<(), Error> {
+        let selectors = &self.selectors.execute;
+        log::info!("click listbox");
+        self.client
+            .wait_for_find(selectors.message_listbox.locator())
+            .await?
+            .click()
+            .await?;
+
+        log::info!("choose {:?}", method);
+        let option = selectors.message_option.with(method);
+        find(&mut self.client, &option).await?.click().await?;
+        Ok(())
+    }
+
+    /// Sends the message as an RPC call, the result is not persisted on chain.
+    pub async fn send_as_rpc(&mut self) -> Result<(), Error> {
+        let selectors = self.selectors.clone();
+        self.send_as(
+            &selectors.execute.rpc_listbox,
+            &selectors.execute.rpc_option,
+        )
+        .await
+    }
+
+    /// Sends the message as a transaction.
+    pub async fn send_as_transaction(&mut self) -> Result<(), Error> {
+        let selectors = self.selectors.clone();
+        self.send_as(
+            &selectors.execute.transaction_listbox,
+            &selectors.execute.transaction_option,
+        )
+        .await
+    }
+
+    /// Chooses `option` in the listbox for RPC call vs. transaction.
+    async fn send_as(
+        &mut self,
+        listbox: &Selector,
+        option: &Selector,
+    ) -> Result<(), Error> {
+        log::info!("open listbox for rpc vs. transaction");
+        find(&mut self.client, listbox).await?.click().await?;
+
+        log::info!("select {:?}", option.as_str());
+        find(&mut self.client, option).await?.click().await?;
+        Ok(())
+    }
+
+    /// Chooses the account `name` as the caller.
+    pub async fn select_caller(&mut self, name: &str) -> Result<(), Error> {
+        let listbox = &self.selectors.execute.caller_listbox;
+        select_account(&mut self.client, &self.selectors, listbox, name).await
+    }
+
+    /// Sets the payment which is sent with the message to `payment` in `unit`.
+    pub async fn set_payment(&mut self, payment: &str, unit: &str) -> Result<(), Error> {
+        let selectors = &self.selectors.execute;
+        log::info!("open listbox for payment units");
+        find(&mut self.client, &selectors.payment_unit_listbox.with(unit))
+            .await?
+            .click()
+            .await?;
+
+        log::info!("click payment unit option {}", unit);
+        self.client
+            .wait_for_find(selectors.payment_unit_option.with(unit).locator())
+            .await?
+            .click()
+            .await?;
+
+        log::info!("entering payment {:?}", payment);
+        set_input(&mut self.client, &selectors.payment_input, payment).await
+    }
+
+    /// Sets the maximum gas allowed to `max_gas`, instead of using the estimated
+    /// gas.
+    pub async fn set_max_gas(&mut self, max_gas: &str) -> Result<(), Error> {
+        let selectors = &self.selectors.execute;
+        // the checkbox only exists if the UI is able to estimate the gas
+        log::info!("unset 'use estimated gas' checkbox if it exists");
+        if let Ok(checkbox) = self
+            .client
+            .find(selectors.estimated_gas_checkbox.locator())
+            .await
+        {
+            checkbox.click().await?;
+        }
+
+        log::info!("entering max gas {:?}", max_gas);
+        set_input(&mut self.client, &selectors.max_gas_input, max_gas).await
+    }
+
+    /// Enters `value` into the argument `key` of the message.
+    pub async fn set_argument(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        log::info!("entering {:?} into {:?}", value, key);
+        let input = self.selectors.execute.argument_input.with(key);
+        set_input(&mut self.client, &input, value).await
+    }
+
+    /// Sends the message.
+    pub async fn call(&mut self) -> Result<(), Error> {
+        log::info!("click call");
+        find(&mut self.client, &self.selectors.execute.call)
+            .await?
+            .click()
+            .await?;
+        Ok(())
+    }
+
+    /// Submits the transaction in the signing modal, which opens after `call`.
+    ///
+    /// The `password` unlocks the caller if it is a custom account.
+    pub async fn sign_and_submit(&mut self, password: Option<&str>) -> Result<(), Error> {
+        sign_and_submit(&mut self.client, &self.selectors, password).await
+    }
+
+    /// Waits for the output of the RPC call and returns it.
+    pub async fn outcome(&mut self) -> Result<String, Error> {
+        let txt = self
+            .client
+            .wait_for_find(self.selectors.execute.outcome.locator())
+            .await?
+            .text()
+            .await?;
+        log::info!("outcomes value {:?}", txt);
+        Ok(txt)
+    }
+}
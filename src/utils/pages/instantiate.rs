@@ -0,0 +1,206 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The form of the `canvas-ui` with which an uploaded contract is instantiated.
+
+use super::{
+    find,
+    goto,
+    select_account,
+    set_input,
+    sign_and_submit,
+    SettingsPage,
+};
+use crate::utils::{
+    backend::Error,
+    selectors::Selectors,
+    wait::Wait,
+};
+use fantoccini::Client;
+use regex::Regex;
+use std::{
+    sync::Arc,
+    time::Duration,
+};
+
+/// The instantiation form, shown after a contract bundle was uploaded.
+pub struct InstantiatePage {
+    client: Client,
+    selectors: Arc<Selectors>,
+}
+
+impl InstantiatePage {
+    /// Creates the page for the currently opened upload form.
+    pub fn new(client: &Client, selectors: &Arc<Selectors>) -> Self {
+        Self {
+            client: client.clone(),
+            selectors: selectors.clone(),
+        }
+    }
+
+    /// Opens the form at `url`, e.g. `#/instantiate/<code hash>` for code which
+    /// is already stored.
+    pub async fn open(
+        client: &Client,
+        selectors: &Arc<Selectors>,
+        url: &str,
+    ) -> Result<Self, Error> {
+        let mut client = client.clone();
+        log::info!("opening {:?}", url);
+        goto(&mut client, url).await?;
+        Ok(Self {
+            client,
+            selectors: selectors.clone(),
+        })
+    }
+
+    /// Opens the details of the constructor, which contain its arguments.
+    pub async fn open_constructor_details(&mut self) -> Result<(), Error> {
+        // toggling the settings twice brings the constructor details into view
+        let mut settings = SettingsPage::new(&self.client, &self.selectors);
+        settings.toggle().await?;
+        settings.toggle().await?;
+
+        // The settings slide in, clicking the details before the animation finished
+        // would miss them
+        log::info!("click details");
+        Wait::new("the 'Constructor Details' to stop moving")
+            .until_animation_finished(
+                &self.client,
+                self.selectors.instantiate.constructor_details.locator(),
+            )
+            .await?
+            .click()
+            .await?;
+        Ok(())
+    }
+
+    /// Chooses the account `name` as the instantiation account.
+    pub async fn select_caller(&mut self, name: &str) -> Result<(), Error> {
+        let listbox = &self.selectors.instantiate.caller_listbox;
+        select_account(&mut self.client, &self.selectors, listbox, name).await
+    }
+
+    /// Enters `value` into the argument `key` of the constructor.
+    pub async fn set_argument(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        log::info!("inserting '{}' into input field '{}'", value, key);
+        let input = self.selectors.instantiate.argument_input.with(key);
+        // this also clears the default `0x000...` input from the field
+        set_input(&mut self.client, &input, value).await
+    }
+
+    /// Chooses the constructor `name`.
+    pub async fn select_constructor(&mut self, name: &str) -> Result<(), Error> {
+        let selectors = &self.selectors.instantiate;
+        log::info!("click constructor list box");
+        self.client
+            .wait_for_find(selectors.constructor_listbox.locator())
+            .await?
+            .click()
+            .await?;
+
+        log::info!("click constructor option {}", name);
+        let option = selectors.constructor_option.with(name);
+        self.client
+            .wait_for_find(option.locator())
+            .await?
+            .click()
+            .await?;
+        Ok(())
+    }
+
+    /// Sets the endowment to `value` in `unit`.
+    pub async fn set_endowment(&mut self, value: &str, unit: &str) -> Result<(), Error> {
+        let selectors = &self.selectors.instantiate;
+        log::info!("set endowment to {}", value);
+        set_input(&mut self.client, &selectors.endowment_input, value).await?;
+
+        log::info!("click endowment list box");
+        self.client
+            .wait_for_find(selectors.endowment_listbox.locator())
+            .await?
+            .click()
+            .await?;
+
+        log::info!("click endowment unit option {}", unit);
+        let option = selectors.endowment_unit_option.with(unit);
+        self.client
+            .wait_for_find(option.locator())
+            .await?
+            .click()
+            .await?;
+        Ok(())
+    }
+
+    /// Toggles "Unique Instantiation Salt", so that the same code can be
+    /// instantiated more than once with the same arguments.
+    pub async fn toggle_unique_salt(&mut self) -> Result<(), Error> {
+        log::info!("Check 'Unique Instantiation Salt' checkbox");
+        find(
+            &mut self.client,
+            &self.selectors.instantiate.unique_salt_toggle,
+        )
+        .await?
+        .click()
+        .await?;
+        Ok(())
+    }
+
+    /// Instantiates the contract and waits until the instantiation succeeded.
+    ///
+    /// The `password` unlocks the caller if it is a custom account.
+    pub async fn instantiate(&mut self, password: Option<&str>) -> Result<(), Error> {
+        log::info!("click instantiate");
+        find(&mut self.client, &self.selectors.instantiate.instantiate)
+            .await?
+            .click()
+            .await?;
+        sign_and_submit(&mut self.client, &self.selectors, password).await?;
+
+        Wait::new("the instantiation")
+            .timeout(Duration::from_secs(60))
+            .until_text_present(
+                &self.client,
+                &self.selectors.instantiate.instantiated_text,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Opens the execute page of the instantiated contract and returns its
+    /// address.
+    pub async fn execute_contract(&mut self) -> Result<String, Error> {
+        let selectors = &self.selectors.instantiate;
+        log::info!("click execute");
+        find(&mut self.client, &selectors.execute_contract)
+            .await?
+            .click()
+            .await?;
+
+        let re = Regex::new(&selectors.address_pattern)
+            .map_err(|err| Error::Other(Box::new(err)))?;
+        let curr_client_url = self.client.current_url().await?;
+        let addr = re
+            .captures(curr_client_url.as_str())
+            .and_then(|captures| captures.get(1))
+            .ok_or_else(|| {
+                Error::AddressUnparseable {
+                    url: curr_client_url.to_string(),
+                }
+            })?
+            .as_str();
+        log::info!("contract address {:?}", addr);
+        Ok(String::from(addr))
+    }
+}
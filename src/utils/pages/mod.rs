@@ -0,0 +1,131 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Page objects for the screens of the `canvas-ui`.
+//!
+//! Each page owns the locators of its elements and offers the actions which the
+//! tests need. The locators are taken from the `Selectors` of the UI release, so
+//! if the markup of the UI changes only the selector profile has to be adapted.
+//!
+//! The actions return the `Error` of the backend, if an element is missing it
+//! names the selector of the element.
+
+mod accounts;
+mod execute;
+mod instantiate;
+mod notifications;
+mod settings;
+mod upload;
+
+pub use self::{
+    accounts::AccountsPage,
+    execute::ExecutePage,
+    instantiate::InstantiatePage,
+    notifications::NotificationsPanel,
+    settings::SettingsPage,
+    upload::UploadPage,
+};
+
+use super::{
+    backend::Error,
+    selectors::{
+        Selector,
+        Selectors,
+    },
+};
+use fantoccini::{
+    error::CmdError,
+    Client,
+    Element,
+};
+
+/// Opens `url`.
+async fn goto(client: &mut Client, url: &str) -> Result<(), Error> {
+    client.goto(url).await.map_err(|source| {
+        Error::Navigation {
+            url: url.to_string(),
+            source,
+        }
+    })
+}
+
+/// Returns the element matching `selector`.
+async fn find(client: &mut Client, selector: &Selector) -> Result<Element, Error> {
+    client.find(selector.locator()).await.map_err(|err| {
+        match err {
+            CmdError::NoSuchElement(_) => {
+                Error::ElementNotFound {
+                    locator: selector.as_str().to_string(),
+                }
+            }
+            err => Error::WebDriver(err),
+        }
+    })
+}
+
+/// Clicks "Sign & Submit" in the signing modal, after entering the `password`
+/// of a custom account.
+async fn sign_and_submit(
+    client: &mut Client,
+    selectors: &Selectors,
+    password: Option<&str>,
+) -> Result<(), Error> {
+    if let Some(password) = password {
+        log::info!("entering the password of the signing account");
+        client
+            .wait_for_find(selectors.common.signing_password_input.locator())
+            .await?
+            .send_keys(password)
+            .await?;
+    }
+    log::info!("click sign and submit");
+    client
+        .wait_for_find(selectors.common.sign_and_submit.locator())
+        .await?
+        .click()
+        .await?;
+    Ok(())
+}
+
+/// Opens the account listbox `listbox` and chooses the account `name`.
+async fn select_account(
+    client: &mut Client,
+    selectors: &Selectors,
+    listbox: &Selector,
+    name: &str,
+) -> Result<(), Error> {
+    log::info!("click listbox for accounts");
+    client
+        .wait_for_find(listbox.locator())
+        .await?
+        .click()
+        .await?;
+
+    log::info!("choose {:?}", name);
+    let option = selectors.common.account_option.with(&name.to_lowercase());
+    find(client, &option).await?.click().await?;
+    Ok(())
+}
+
+/// Replaces the contents of the text input `input` with `value`.
+async fn set_input(
+    client: &mut Client,
+    input: &Selector,
+    value: &str,
+) -> Result<(), Error> {
+    let mut input = find(client, input).await?;
+    input.clear().await?;
+    input.send_keys(value).await?;
+    Ok(())
+}
@@ -0,0 +1,145 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The notifications which the `canvas-ui` shows for extrinsics.
+
+use crate::utils::{
+    backend::{
+        Error,
+        Event,
+    },
+    selectors::Selectors,
+    wait::Wait,
+};
+use fantoccini::{
+    error::CmdError,
+    Client,
+};
+use serde_json::json;
+use std::{
+    sync::Arc,
+    time::Duration,
+};
+
+/// Hides all notifications matching the CSS selector in the first argument.
+const HIDE_ALL: &str =
+    "document.querySelectorAll(arguments[0]).forEach(function (notification) {\
+        notification.style.display = 'none';\
+    });";
+
+/// The notifications in the corner of every page.
+pub struct NotificationsPanel {
+    client: Client,
+    selectors: Arc<Selectors>,
+}
+
+impl NotificationsPanel {
+    /// Creates the panel for the currently opened page.
+    pub fn new(client: &Client, selectors: &Arc<Selectors>) -> Self {
+        Self {
+            client: client.clone(),
+            selectors: selectors.clone(),
+        }
+    }
+
+    /// Waits until an extrinsic was queued for signing.
+    pub async fn wait_until_queued(&mut self) -> Result<(), Error> {
+        self.client
+            .wait_for_find(self.selectors.notifications.queued.locator())
+            .await?;
+        Ok(())
+    }
+
+    /// Waits until the submitted extrinsic either succeeded or failed.
+    pub async fn wait_for_extrinsic_result(&mut self) -> Result<(), Error> {
+        Wait::new("either the success or the failure notification")
+            .timeout(Duration::from_secs(60))
+            .interval(Duration::from_millis(500))
+            .until(|| {
+                let mut client = self.client.clone();
+                let selectors = self.selectors.clone();
+                async move {
+                    let result = &selectors.notifications.extrinsic_result;
+                    match client.find(result.locator()).await {
+                        Ok(header) => Ok(Some(header)),
+                        Err(CmdError::NoSuchElement(_)) => Ok(None),
+                        Err(err) => Err(err),
+                    }
+                }
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Returns the events of all notifications.
+    pub async fn events(&mut self) -> Result<Vec<Event>, Error> {
+        let selectors = &self.selectors.notifications;
+        let descriptions = self
+            .client
+            .find_all(selectors.description.locator())
+            .await?;
+        log::info!("found {:?} status messages", descriptions.len());
+        let mut events = Vec::new();
+        for mut description in descriptions {
+            let header = description
+                .find(selectors.header.locator())
+                .await?
+                .text()
+                .await?;
+            let status = description
+                .find(selectors.status.locator())
+                .await?
+                .text()
+                .await?;
+            log::info!("found status message {:?} with {:?}", header, status);
+            events.push(Event::from_notification(&header, &status));
+        }
+        Ok(events)
+    }
+
+    /// Returns the text of all notifications, one per line.
+    pub async fn text(&mut self) -> Result<String, Error> {
+        let descriptions = self
+            .client
+            .find_all(self.selectors.notifications.description.locator())
+            .await?;
+        let mut text = String::new();
+        for mut description in descriptions {
+            text.push_str(&description.text().await?.replace('\n', " "));
+            text.push('\n');
+        }
+        Ok(text)
+    }
+
+    /// Dismisses all notifications.
+    pub async fn dismiss_all(&mut self) -> Result<(), Error> {
+        log::info!("click dismiss");
+        self.client
+            .wait_for_find(self.selectors.notifications.dismiss_all.locator())
+            .await?
+            .click()
+            .await?;
+        Ok(())
+    }
+
+    /// Hides all notifications without waiting for their disappearance animation,
+    /// otherwise they might occlude buttons.
+    pub async fn hide(&mut self) -> Result<(), Error> {
+        let notification = self.selectors.notifications.notification.as_str();
+        self.client
+            .execute(HIDE_ALL, vec![json!(notification)])
+            .await?;
+        Ok(())
+    }
+}
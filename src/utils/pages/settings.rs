@@ -0,0 +1,58 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The settings in the sidebar of the `canvas-ui`.
+
+use super::find;
+use crate::utils::{
+    backend::Error,
+    selectors::Selectors,
+    wait::Wait,
+};
+use fantoccini::Client;
+use std::sync::Arc;
+
+/// The settings in the sidebar, which are part of every page.
+pub struct SettingsPage {
+    client: Client,
+    selectors: Arc<Selectors>,
+}
+
+impl SettingsPage {
+    /// Creates the page for the currently opened UI.
+    pub fn new(client: &Client, selectors: &Arc<Selectors>) -> Self {
+        Self {
+            client: client.clone(),
+            selectors: selectors.clone(),
+        }
+    }
+
+    /// Waits until the settings are visible, which means that the page is ready.
+    pub async fn wait_until_loaded(&mut self) -> Result<(), Error> {
+        Wait::new("the settings in the sidebar")
+            .until_visible(&self.client, self.selectors.settings.toggle.locator())
+            .await?;
+        Ok(())
+    }
+
+    /// Shows the settings if they are hidden and hides them otherwise.
+    pub async fn toggle(&mut self) -> Result<(), Error> {
+        log::info!("click settings");
+        find(&mut self.client, &self.selectors.settings.toggle)
+            .await?
+            .click()
+            .await?;
+        Ok(())
+    }
+}
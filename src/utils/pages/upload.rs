@@ -0,0 +1,154 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The page of the `canvas-ui` on which contracts are uploaded.
+
+use super::{
+    find,
+    goto,
+    SettingsPage,
+};
+use crate::utils::{
+    backend::Error,
+    selectors::{
+        Selector,
+        Selectors,
+    },
+    wait::{
+        Wait,
+        WaitError,
+    },
+};
+use fantoccini::Client;
+use serde_json::json;
+use std::{
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
+
+/// Clicks the element matching the CSS selector `arguments[0]`, returns `false`
+/// if there is none.
+const CLICK_SCRIPT: &str = "\
+    var el = document.querySelector(arguments[0]);\
+    if (!el) return false;\
+    el.click();\
+    return true;";
+
+/// Dispatches a `change` event on the element matching the CSS selector
+/// `arguments[0]`, returns `false` if there is none.
+const CHANGE_SCRIPT: &str = "\
+    var el = document.querySelector(arguments[0]);\
+    if (!el) return false;\
+    el.dispatchEvent(new Event('change', { bubbles: true }));\
+    return true;";
+
+/// The page at `#/upload`.
+pub struct UploadPage {
+    client: Client,
+    selectors: Arc<Selectors>,
+}
+
+impl UploadPage {
+    /// Opens the page at `url` and waits until it is ready.
+    pub async fn open(
+        client: &Client,
+        selectors: &Arc<Selectors>,
+        url: &str,
+    ) -> Result<Self, Error> {
+        let mut client = client.clone();
+        log::info!("opening {:?}", url);
+        goto(&mut client, url).await?;
+        SettingsPage::new(&client, selectors)
+            .wait_until_loaded()
+            .await?;
+        Ok(Self {
+            client,
+            selectors: selectors.clone(),
+        })
+    }
+
+    /// Closes the introduction, if it is shown.
+    ///
+    /// The "Skip Intro" button sometimes appears after a bit of time and sometimes
+    /// it doesn't, e.g. if it was already clicked away in this browser session.
+    pub async fn skip_intro(&mut self) -> Result<(), Error> {
+        match Wait::new("the 'Skip Intro' button")
+            .timeout(Duration::from_secs(3))
+            .until_clickable(&self.client, self.selectors.upload.skip_intro.locator())
+            .await
+        {
+            Ok(skip_button) => {
+                skip_button.click().await?;
+            }
+            Err(WaitError::Timeout { .. }) => {
+                log::info!("did not find 'Skip Intro' button, ignoring it.")
+            }
+            Err(err) => return Err(err.into()),
+        }
+        Ok(())
+    }
+
+    /// Opens the upload form.
+    pub async fn open_upload_form(&mut self) -> Result<(), Error> {
+        log::info!("click upload");
+        self.client
+            .wait_for_find(self.selectors.upload.upload_and_instantiate.locator())
+            .await?
+            .click()
+            .await?;
+        Ok(())
+    }
+
+    /// Chooses the account `name` as the deployment account.
+    pub async fn select_deployment_account(&mut self, name: &str) -> Result<(), Error> {
+        let selectors = &self.selectors.upload;
+        log::info!("click combobox");
+        run_script(&mut self.client, CLICK_SCRIPT, &selectors.account_combobox).await?;
+
+        log::info!("click {}", name);
+        let option = selectors.account_option.with(&name.to_lowercase());
+        run_script(&mut self.client, CLICK_SCRIPT, &option).await
+    }
+
+    /// Uploads the contract bundle at `path`.
+    pub async fn upload(&mut self, path: &Path) -> Result<(), Error> {
+        let file_input = &self.selectors.upload.file_input;
+        log::info!("uploading {:?}", path);
+        find(&mut self.client, file_input)
+            .await?
+            .send_keys(&path.display().to_string())
+            .await?;
+        run_script(&mut self.client, CHANGE_SCRIPT, file_input).await
+    }
+}
+
+/// Runs `script` for the element matching the CSS `selector`.
+///
+/// The script returns `false` if there is no such element.
+async fn run_script(
+    client: &mut Client,
+    script: &str,
+    selector: &Selector,
+) -> Result<(), Error> {
+    let found = client
+        .execute(script, vec![json!(selector.as_str())])
+        .await?;
+    if found.as_bool() != Some(true) {
+        return Err(Error::ElementNotFound {
+            locator: selector.as_str().to_string(),
+        })
+    }
+    Ok(())
+}
@@ -0,0 +1,613 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! SCALE encoding of message arguments and decoding of return values, driven by
+//! the type registry in the metadata of a contract bundle.
+
+use super::value::Value as Decoded;
+use codec::{
+    Compact,
+    Decode,
+    Encode,
+};
+use serde_json::Value;
+
+/// Returns the type with `id` from the metadata's type registry `types`.
+///
+/// Older metadata versions reference the types by their 1-based position in the
+/// registry, newer ones carry an explicit `id` for each type.
+fn resolve_type(types: &[Value], id: u64) -> Result<&Value, String> {
+    let ty = match types.first().and_then(|ty| ty.get("id")) {
+        Some(_) => {
+            types
+                .iter()
+                .find(|ty| ty.get("id").and_then(Value::as_u64) == Some(id))
+                .and_then(|ty| ty.get("type"))
+        }
+        None => types.get((id as usize).wrapping_sub(1)),
+    };
+    ty.ok_or_else(|| format!("type {} not found in the metadata", id))
+}
+
+/// Returns the definition of the type with `id`.
+fn resolve(types: &[Value], id: u64) -> Result<&Value, String> {
+    resolve_type(types, id)?
+        .get("def")
+        .ok_or_else(|| format!("type {} has no definition", id))
+}
+
+/// Returns the last segment of the path of the type with `id`, e.g. `AccountId`
+/// or `Option`. Primitives, arrays and tuples have no path.
+pub fn type_name(types: &[Value], id: u64) -> Option<&str> {
+    resolve_type(types, id)
+        .ok()?
+        .get("path")?
+        .as_array()?
+        .last()?
+        .as_str()
+}
+
+/// Returns the type id of the value in the `Some` variant of the `Option` with
+/// `id`.
+pub fn option_param(types: &[Value], id: u64) -> Result<u64, String> {
+    let variants = resolve(types, id)?
+        .get("variant")
+        .and_then(|variant| variant.get("variants"))
+        .and_then(Value::as_array)
+        .ok_or_else(|| format!("type {} is no `Option`", id))?;
+    let some = variants
+        .iter()
+        .find(|var| var.get("name").and_then(Value::as_str) == Some("Some"))
+        .and_then(|var| var.get("fields"))
+        .and_then(Value::as_array)
+        .and_then(|fields| fields.first())
+        .ok_or_else(|| format!("type {} has no `Some` variant", id))?;
+    type_id(some)
+}
+
+/// Returns the type id a `{ "type": … }` reference points to.
+fn type_id(reference: &Value) -> Result<u64, String> {
+    reference
+        .get("type")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| format!("invalid type reference {}", reference))
+}
+
+/// Returns `true` if the type with `id` is `u8`.
+fn is_u8(types: &[Value], id: u64) -> bool {
+    matches!(resolve(types, id), Ok(def) if def.get("primitive") == Some(&Value::from("u8")))
+}
+
+/// Encodes the textual `input` as a value of the type `id` and appends it to `out`.
+///
+/// Byte arrays and sequences are expected as `0x`-prefixed hex, byte arrays which
+/// are given less bytes than their length are padded with zeros.
+pub fn encode(
+    types: &[Value],
+    id: u64,
+    input: &str,
+    out: &mut Vec<u8>,
+) -> Result<(), String> {
+    let def = resolve(types, id)?;
+    let input = input.trim();
+    if let Some(primitive) = def.get("primitive").and_then(Value::as_str) {
+        encode_primitive(primitive, input, out)
+    } else if let Some(array) = def.get("array") {
+        let len = array.get("len").and_then(Value::as_u64).unwrap_or_default() as usize;
+        if !is_u8(types, type_id(array)?) {
+            return Err(format!("unsupported array input {:?}", input))
+        }
+        let mut bytes = decode_hex(input)?;
+        if bytes.len() > len {
+            return Err(format!("{:?} does not fit into [u8; {}]", input, len))
+        }
+        bytes.resize(len, 0);
+        out.extend(bytes);
+        Ok(())
+    } else if let Some(sequence) = def.get("sequence") {
+        if !is_u8(types, type_id(sequence)?) {
+            return Err(format!("unsupported sequence input {:?}", input))
+        }
+        decode_hex(input)?.encode_to(out);
+        Ok(())
+    } else if let Some(compact) = def.get("compact") {
+        let _ = type_id(compact)?;
+        let value = input
+            .parse::<u128>()
+            .map_err(|err| format!("{:?} is no valid number: {:?}", input, err))?;
+        Compact(value).encode_to(out);
+        Ok(())
+    } else if let Some(composite) = def.get("composite") {
+        match composite.get("fields").and_then(Value::as_array) {
+            Some(fields) if fields.len() == 1 => {
+                encode(types, type_id(&fields[0])?, input, out)
+            }
+            Some(fields) if !fields.is_empty() => {
+                Err(format!("unsupported composite input {:?}", input))
+            }
+            _ => Ok(()),
+        }
+    } else if let Some(variant) = def.get("variant") {
+        encode_variant(types, variant, input, out)
+    } else if let Some(tuple) = def.get("tuple").and_then(Value::as_array) {
+        if tuple.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("unsupported tuple input {:?}", input))
+        }
+    } else {
+        Err(format!("unsupported type definition {}", def))
+    }
+}
+
+/// Appends the encoding of the default value of the type `id` to `out`.
+///
+/// This mirrors the `canvas-ui`, which submits zero values for arguments the
+/// user did not fill in.
+pub fn encode_default(types: &[Value], id: u64, out: &mut Vec<u8>) -> Result<(), String> {
+    let def = resolve(types, id)?;
+    if let Some(primitive) = def.get("primitive").and_then(Value::as_str) {
+        let input = match primitive {
+            "bool" => "false",
+            "char" => "\0",
+            "str" => "",
+            _ => "0",
+        };
+        encode_primitive(primitive, input, out)
+    } else if let Some(array) = def.get("array") {
+        let len = array.get("len").and_then(Value::as_u64).unwrap_or_default();
+        let elem = type_id(array)?;
+        (0..len).try_for_each(|_| encode_default(types, elem, out))
+    } else if def.get("sequence").is_some() || def.get("compact").is_some() {
+        Compact(0u32).encode_to(out);
+        Ok(())
+    } else if let Some(composite) = def.get("composite") {
+        composite
+            .get("fields")
+            .and_then(Value::as_array)
+            .map(|fields| {
+                fields
+                    .iter()
+                    .try_for_each(|field| encode_default(types, type_id(field)?, out))
+            })
+            .unwrap_or(Ok(()))
+    } else if let Some(variant) = def.get("variant") {
+        let first = variant
+            .get("variants")
+            .and_then(|variants| variants.get(0))
+            .ok_or_else(|| format!("variant without variants {}", variant))?;
+        out.push(
+            first
+                .get("discriminant")
+                .and_then(Value::as_u64)
+                .unwrap_or(0) as u8,
+        );
+        first
+            .get("fields")
+            .and_then(Value::as_array)
+            .map(|fields| {
+                fields
+                    .iter()
+                    .try_for_each(|field| encode_default(types, type_id(field)?, out))
+            })
+            .unwrap_or(Ok(()))
+    } else if let Some(tuple) = def.get("tuple").and_then(Value::as_array) {
+        tuple.iter().try_for_each(|elem| {
+            let id = elem
+                .as_u64()
+                .ok_or_else(|| format!("invalid tuple element {}", elem))?;
+            encode_default(types, id, out)
+        })
+    } else {
+        Err(format!("unsupported type definition {}", def))
+    }
+}
+
+/// Encodes `input` as a primitive.
+fn encode_primitive(
+    primitive: &str,
+    input: &str,
+    out: &mut Vec<u8>,
+) -> Result<(), String> {
+    fn parse<T: std::str::FromStr>(input: &str) -> Result<T, String> {
+        input
+            .parse::<T>()
+            .map_err(|_| format!("{:?} cannot be parsed into the expected type", input))
+    }
+    match primitive {
+        "bool" => parse::<bool>(input)?.encode_to(out),
+        "char" => (parse::<char>(input)? as u32).encode_to(out),
+        "str" => input.encode_to(out),
+        "u8" => parse::<u8>(input)?.encode_to(out),
+        "u16" => parse::<u16>(input)?.encode_to(out),
+        "u32" => parse::<u32>(input)?.encode_to(out),
+        "u64" => parse::<u64>(input)?.encode_to(out),
+        "u128" => parse::<u128>(input)?.encode_to(out),
+        "i8" => parse::<i8>(input)?.encode_to(out),
+        "i16" => parse::<i16>(input)?.encode_to(out),
+        "i32" => parse::<i32>(input)?.encode_to(out),
+        "i64" => parse::<i64>(input)?.encode_to(out),
+        "i128" => parse::<i128>(input)?.encode_to(out),
+        other => return Err(format!("unsupported primitive {:?}", other)),
+    }
+    Ok(())
+}
+
+/// Encodes `input` as one of the variants in `variant`.
+///
+/// Either the name of a variant without fields or, for an `Option`, the value of
+/// the `Some` variant is accepted.
+fn encode_variant(
+    types: &[Value],
+    variant: &Value,
+    input: &str,
+    out: &mut Vec<u8>,
+) -> Result<(), String> {
+    let variants = variant
+        .get("variants")
+        .and_then(Value::as_array)
+        .ok_or_else(|| format!("invalid variant definition {}", variant))?;
+    for (index, var) in variants.iter().enumerate() {
+        let index = var
+            .get("discriminant")
+            .and_then(Value::as_u64)
+            .unwrap_or(index as u64) as u8;
+        if var.get("name").and_then(Value::as_str) == Some(input) {
+            out.push(index);
+            return Ok(())
+        }
+        if var.get("name").and_then(Value::as_str) == Some("Some") && input != "None" {
+            let field = var
+                .get("fields")
+                .and_then(|fields| fields.get(0))
+                .ok_or_else(|| format!("invalid variant definition {}", variant))?;
+            out.push(index);
+            return encode(types, type_id(field)?, input, out)
+        }
+    }
+    Err(format!(
+        "{:?} matches none of the variants {}",
+        input, variant
+    ))
+}
+
+/// Decodes a value of the type `id` from `input` and returns it formatted
+/// as text.
+///
+/// Byte arrays and sequences are formatted as `0x`-prefixed hex.
+pub fn decode(types: &[Value], id: u64, input: &mut &[u8]) -> Result<String, String> {
+    decode_value(types, id, input).map(|value| value.to_string())
+}
+
+/// Decodes a value of the type `id` from `input`.
+pub fn decode_value(
+    types: &[Value],
+    id: u64,
+    input: &mut &[u8],
+) -> Result<Decoded, String> {
+    let def = resolve(types, id)?;
+    if let Some(primitive) = def.get("primitive").and_then(Value::as_str) {
+        decode_primitive(primitive, input)
+    } else if let Some(array) = def.get("array") {
+        let len = array.get("len").and_then(Value::as_u64).unwrap_or_default() as usize;
+        let elem = type_id(array)?;
+        if is_u8(types, elem) {
+            if input.len() < len {
+                return Err(format!("not enough bytes to decode [u8; {}]", len))
+            }
+            let (bytes, rest) = input.split_at(len);
+            *input = rest;
+            Ok(Decoded::Bytes(bytes.to_vec()))
+        } else {
+            let elems = (0..len)
+                .map(|_| decode_value(types, elem, input))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(Decoded::Seq(elems))
+        }
+    } else if let Some(sequence) = def.get("sequence") {
+        let elem = type_id(sequence)?;
+        let len = <Compact<u32>>::decode(input)
+            .map_err(|err| err.to_string())?
+            .0;
+        if is_u8(types, elem) {
+            let len = len as usize;
+            if input.len() < len {
+                return Err(format!("not enough bytes to decode Vec<u8> of {}", len))
+            }
+            let (bytes, rest) = input.split_at(len);
+            *input = rest;
+            Ok(Decoded::Bytes(bytes.to_vec()))
+        } else {
+            let elems = (0..len)
+                .map(|_| decode_value(types, elem, input))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(Decoded::Seq(elems))
+        }
+    } else if let Some(compact) = def.get("compact") {
+        let _ = type_id(compact)?;
+        let value = <Compact<u128>>::decode(input).map_err(|err| err.to_string())?;
+        Ok(Decoded::UInt(value.0))
+    } else if let Some(composite) = def.get("composite") {
+        let empty = Vec::new();
+        let fields = composite
+            .get("fields")
+            .and_then(Value::as_array)
+            .unwrap_or(&empty);
+        let mut fields = decode_fields(types, fields, input)?;
+        match fields.as_slice() {
+            [(None, _)] => Ok(fields.remove(0).1),
+            _ => Ok(Decoded::Composite(fields)),
+        }
+    } else if let Some(variant) = def.get("variant") {
+        let index = u8::decode(input).map_err(|err| err.to_string())?;
+        let var = variant
+            .get("variants")
+            .and_then(Value::as_array)
+            .and_then(|variants| {
+                variants.iter().enumerate().find(|(pos, var)| {
+                    var.get("discriminant")
+                        .and_then(Value::as_u64)
+                        .unwrap_or(*pos as u64)
+                        == index as u64
+                })
+            })
+            .map(|(_, var)| var)
+            .ok_or_else(|| format!("variant index {} not found in {}", index, variant))?;
+        let name = var.get("name").and_then(Value::as_str).unwrap_or_default();
+        let empty = Vec::new();
+        let fields = var
+            .get("fields")
+            .and_then(Value::as_array)
+            .unwrap_or(&empty);
+        Ok(Decoded::Variant(
+            name.to_string(),
+            decode_fields(types, fields, input)?,
+        ))
+    } else if let Some(tuple) = def.get("tuple").and_then(Value::as_array) {
+        let elems = tuple
+            .iter()
+            .map(|elem| {
+                let id = elem
+                    .as_u64()
+                    .ok_or_else(|| format!("invalid tuple element {}", elem))?;
+                decode_value(types, id, input)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Decoded::Tuple(elems))
+    } else {
+        Err(format!("unsupported type definition {}", def))
+    }
+}
+
+/// Decodes the `fields` of a composite or variant together with their names.
+fn decode_fields(
+    types: &[Value],
+    fields: &[Value],
+    input: &mut &[u8],
+) -> Result<Vec<(Option<String>, Decoded)>, String> {
+    fields
+        .iter()
+        .map(|field| {
+            let value = decode_value(types, type_id(field)?, input)?;
+            let name = field.get("name").and_then(Value::as_str).map(String::from);
+            Ok((name, value))
+        })
+        .collect()
+}
+
+/// Decodes a primitive from `input`.
+fn decode_primitive(primitive: &str, input: &mut &[u8]) -> Result<Decoded, String> {
+    fn dec<T: Decode>(input: &mut &[u8]) -> Result<T, String> {
+        T::decode(input).map_err(|err| err.to_string())
+    }
+    match primitive {
+        "bool" => dec::<bool>(input).map(Decoded::Bool),
+        "char" => {
+            let c = dec::<u32>(input)?;
+            std::char::from_u32(c)
+                .map(Decoded::Char)
+                .ok_or_else(|| format!("{} is no valid char", c))
+        }
+        "str" => dec::<String>(input).map(Decoded::Str),
+        "u8" => dec::<u8>(input).map(|val| Decoded::UInt(val.into())),
+        "u16" => dec::<u16>(input).map(|val| Decoded::UInt(val.into())),
+        "u32" => dec::<u32>(input).map(|val| Decoded::UInt(val.into())),
+        "u64" => dec::<u64>(input).map(|val| Decoded::UInt(val.into())),
+        "u128" => dec::<u128>(input).map(Decoded::UInt),
+        "i8" => dec::<i8>(input).map(|val| Decoded::Int(val.into())),
+        "i16" => dec::<i16>(input).map(|val| Decoded::Int(val.into())),
+        "i32" => dec::<i32>(input).map(|val| Decoded::Int(val.into())),
+        "i64" => dec::<i64>(input).map(|val| Decoded::Int(val.into())),
+        "i128" => dec::<i128>(input).map(Decoded::Int),
+        other => Err(format!("unsupported primitive {:?}", other)),
+    }
+}
+
+/// Decodes the (optionally `0x`-prefixed) hex string `input`.
+pub fn decode_hex(input: &str) -> Result<Vec<u8>, String> {
+    hex::decode(input.trim_start_matches("0x"))
+        .map_err(|err| format!("{:?} is no valid hex: {:?}", input, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const U32: u64 = 0;
+    const I32: u64 = 1;
+    const ACCOUNT_ID: u64 = 2;
+    const OPTION_U32: u64 = 5;
+    const VEC_U8: u64 = 6;
+    const STRUCT: u64 = 8;
+
+    /// A type registry with the types of the `id`s above.
+    fn types() -> Vec<Value> {
+        let types = serde_json::json!([
+            { "id": 0, "type": { "def": { "primitive": "u32" } } },
+            { "id": 1, "type": { "def": { "primitive": "i32" } } },
+            {
+                "id": 2,
+                "type": {
+                    "def": { "composite": { "fields": [{ "type": 3 }] } },
+                    "path": ["ink_env", "types", "AccountId"],
+                },
+            },
+            { "id": 3, "type": { "def": { "array": { "len": 4, "type": 4 } } } },
+            { "id": 4, "type": { "def": { "primitive": "u8" } } },
+            {
+                "id": 5,
+                "type": {
+                    "def": {
+                        "variant": {
+                            "variants": [
+                                { "name": "None" },
+                                { "name": "Some", "fields": [{ "type": 0 }] },
+                            ],
+                        },
+                    },
+                    "path": ["Option"],
+                },
+            },
+            { "id": 6, "type": { "def": { "sequence": { "type": 4 } } } },
+            { "id": 7, "type": { "def": { "primitive": "bool" } } },
+            {
+                "id": 8,
+                "type": {
+                    "def": {
+                        "composite": {
+                            "fields": [
+                                { "name": "value", "type": 0 },
+                                { "name": "flag", "type": 7 },
+                            ],
+                        },
+                    },
+                },
+            },
+        ]);
+        serde_json::from_value(types).expect("the fixture is malformed")
+    }
+
+    fn encoded(id: u64, input: &str) -> Result<Vec<u8>, String> {
+        let mut out = Vec::new();
+        encode(&types(), id, input, &mut out).map(|()| out)
+    }
+
+    fn decoded(id: u64, mut input: &[u8]) -> Result<Decoded, String> {
+        let value = decode_value(&types(), id, &mut input)?;
+        assert!(input.is_empty(), "{} bytes were not decoded", input.len());
+        Ok(value)
+    }
+
+    #[test]
+    fn encode_primitives() {
+        assert_eq!(encoded(U32, " 42 "), Ok(42u32.encode()));
+        assert_eq!(encoded(I32, "-1"), Ok((-1i32).encode()));
+        assert!(encoded(U32, "-1").is_err());
+        assert!(encoded(U32, "4294967296").is_err());
+    }
+
+    #[test]
+    fn encode_pads_byte_arrays() {
+        assert_eq!(encoded(ACCOUNT_ID, "0x0102"), Ok(vec![1, 2, 0, 0]));
+        assert_eq!(encoded(ACCOUNT_ID, "0x01020304"), Ok(vec![1, 2, 3, 4]));
+        assert!(encoded(ACCOUNT_ID, "0x0102030405").is_err());
+        assert!(encoded(ACCOUNT_ID, "0xzz").is_err());
+    }
+
+    #[test]
+    fn encode_options_and_sequences() {
+        assert_eq!(encoded(OPTION_U32, "None"), Ok(vec![0]));
+        assert_eq!(encoded(OPTION_U32, "7"), Ok(vec![1, 7, 0, 0, 0]));
+        assert_eq!(encoded(VEC_U8, "0xabcd"), Ok(vec![2 << 2, 0xab, 0xcd]));
+    }
+
+    #[test]
+    fn decode_value_roundtrips_encode() {
+        let some = encoded(OPTION_U32, "7").expect("unable to encode");
+        assert_eq!(
+            decoded(OPTION_U32, &some),
+            Ok(Decoded::Variant(
+                "Some".to_string(),
+                vec![(None, Decoded::UInt(7))]
+            ))
+        );
+        assert_eq!(
+            decoded(OPTION_U32, &[0]),
+            Ok(Decoded::Variant("None".to_string(), Vec::new()))
+        );
+        assert_eq!(decoded(I32, &(-5i32).encode()), Ok(Decoded::Int(-5)));
+        // an `AccountId` is represented by its byte array
+        assert_eq!(
+            decoded(ACCOUNT_ID, &[1, 2, 3, 4]),
+            Ok(Decoded::Bytes(vec![1, 2, 3, 4]))
+        );
+        assert_eq!(
+            decoded(VEC_U8, &[2 << 2, 0xab, 0xcd]),
+            Ok(Decoded::Bytes(vec![0xab, 0xcd]))
+        );
+    }
+
+    #[test]
+    fn decode_value_names_the_fields() {
+        let mut input = (3u32, true).encode();
+        input.extend(42u32.encode());
+        let mut input = &input[..];
+
+        assert_eq!(
+            decode_value(&types(), STRUCT, &mut input),
+            Ok(Decoded::Composite(vec![
+                (Some("value".to_string()), Decoded::UInt(3)),
+                (Some("flag".to_string()), Decoded::Bool(true)),
+            ]))
+        );
+        // the rest of the input is left for the next value
+        assert_eq!(input, &42u32.encode()[..]);
+    }
+
+    #[test]
+    fn decode_value_rejects_truncated_input() {
+        assert!(decoded(U32, &[1, 2]).is_err());
+        assert!(decoded(ACCOUNT_ID, &[1, 2, 3]).is_err());
+        assert!(decoded(OPTION_U32, &[2]).is_err());
+    }
+
+    #[test]
+    fn decode_formats_like_the_ui() {
+        let mut input = &[1, 2, 3, 4][..];
+        assert_eq!(
+            decode(&types(), ACCOUNT_ID, &mut input),
+            Ok("0x01020304".to_string())
+        );
+        let mut input = &[1, 7, 0, 0, 0][..];
+        assert_eq!(
+            decode(&types(), OPTION_U32, &mut input),
+            Ok("Some(7)".to_string())
+        );
+    }
+
+    #[test]
+    fn resolve_supports_positional_type_ids() {
+        // older metadata versions have no `id`s, the types are referenced by their
+        // 1-based position
+        let types = vec![
+            serde_json::json!({ "def": { "primitive": "bool" } }),
+            serde_json::json!({ "def": { "primitive": "u32" } }),
+        ];
+        let mut out = Vec::new();
+
+        encode(&types, 2, "3", &mut out).expect("unable to encode");
+        assert_eq!(out, 3u32.encode());
+        assert!(encode(&types, 0, "true", &mut Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,510 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Selectors for the elements of the `canvas-ui`, per release of the UI.
+//!
+//! The markup of the UI differs between its releases, hence the selectors are
+//! not hardcoded but loaded from the profiles in the `selectors` directory. A
+//! profile applies to all releases starting at its `since` version, up to the
+//! `since` version of the next profile.
+//!
+//! The release is taken from the environment variable `CANVAS_UI_VERSION` or
+//! detected from the running UI. If it is unknown the latest profile is used.
+//!
+//! Only the oldest profile has to be complete, every other profile only contains
+//! the selectors which changed since the profile before it. So far
+//! `selectors/default.json` is the only profile, it applies to the releases which
+//! the tests are run against.
+//!
+//! Selectors can be overridden without changing the profiles in this repository,
+//! by putting profiles like `selectors/overrides/example.json` into the directory
+//! `WATERFALL_SELECTORS_OVERRIDES`. They are applied after the profiles with the
+//! same `since` version.
+
+use fantoccini::Locator;
+use serde::Deserialize;
+use serde_json::Value;
+use std::{
+    fs,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+/// Returns the version of the running UI, e.g. `0.1.2`, an empty string if it is
+/// not displayed, or `null` while the UI is not rendered yet.
+pub const DETECT_VERSION_SCRIPT: &str = "\
+    var root = document.getElementById('root');\
+    if (!root || !root.children.length) return null;\
+    var el = document.querySelector('[class*=\"version\" i]');\
+    var version = el && el.textContent.match(/(\\d+\\.\\d+\\.\\d+)/);\
+    return version ? version[1] : '';";
+
+/// A selector for an element, either a CSS selector or an XPath.
+///
+/// A selector may contain a `{}` placeholder, which is replaced with `with`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Selector {
+    Css(String),
+    XPath(String),
+}
+
+impl Selector {
+    /// Returns the locator for this selector.
+    pub fn locator(&self) -> Locator<'_> {
+        match self {
+            Selector::Css(css) => Locator::Css(css),
+            Selector::XPath(path) => Locator::XPath(path),
+        }
+    }
+
+    /// Returns this selector with its `{}` placeholder replaced by `value`.
+    pub fn with(&self, value: &str) -> Selector {
+        match self {
+            Selector::Css(css) => Selector::Css(css.replace("{}", value)),
+            Selector::XPath(path) => Selector::XPath(path.replace("{}", value)),
+        }
+    }
+
+    /// Returns the selector itself, without its kind.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Selector::Css(selector) | Selector::XPath(selector) => selector,
+        }
+    }
+}
+
+/// A profile of selectors for the releases of the UI starting at `since`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Selectors {
+    /// The first release of the UI which this profile applies to.
+    pub since: String,
+    /// Elements which are part of more than one page.
+    pub common: CommonSelectors,
+    /// See `SettingsPage`.
+    pub settings: SettingsSelectors,
+    /// See `UploadPage`.
+    pub upload: UploadSelectors,
+    /// See `InstantiatePage`.
+    pub instantiate: InstantiateSelectors,
+    /// See `ExecutePage`.
+    pub execute: ExecuteSelectors,
+    /// See `AccountsPage`.
+    pub accounts: AccountsSelectors,
+    /// See `NotificationsPanel`.
+    pub notifications: NotificationsSelectors,
+}
+
+/// The selectors of elements which are part of more than one page.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CommonSelectors {
+    /// The button of the signing modal which submits an extrinsic.
+    pub sign_and_submit: Selector,
+    /// The input of the signing modal for the password of a custom account.
+    pub signing_password_input: Selector,
+    /// An account in an opened account listbox, `{}` is the account name.
+    pub account_option: Selector,
+}
+
+/// The selectors of `SettingsPage`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SettingsSelectors {
+    /// The button in the sidebar which toggles the settings.
+    pub toggle: Selector,
+}
+
+/// The selectors of `UploadPage`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct UploadSelectors {
+    /// The button which closes the introduction of the UI.
+    pub skip_intro: Selector,
+    /// The button which opens the upload form.
+    pub upload_and_instantiate: Selector,
+    /// The combobox for the deployment account, has to be a CSS selector.
+    pub account_combobox: Selector,
+    /// An account in the combobox, `{}` is the account name. Has to be a CSS
+    /// selector.
+    pub account_option: Selector,
+    /// The file input of the upload form, has to be a CSS selector.
+    pub file_input: Selector,
+}
+
+/// The selectors of `InstantiatePage`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct InstantiateSelectors {
+    /// The section which contains the details of the constructor.
+    pub constructor_details: Selector,
+    /// The listbox for the account which instantiates the contract.
+    pub caller_listbox: Selector,
+    /// The input for an argument, `{}` is the label of the argument.
+    pub argument_input: Selector,
+    /// The listbox for the constructor.
+    pub constructor_listbox: Selector,
+    /// A constructor in the listbox, `{}` is the name of the constructor.
+    pub constructor_option: Selector,
+    /// The input for the endowment.
+    pub endowment_input: Selector,
+    /// The listbox for the unit of the endowment.
+    pub endowment_listbox: Selector,
+    /// A unit in the listbox, `{}` is the unit.
+    pub endowment_unit_option: Selector,
+    /// The toggle which makes the salt of the instantiation unique.
+    pub unique_salt_toggle: Selector,
+    /// The button which instantiates the contract.
+    pub instantiate: Selector,
+    /// The text which is shown after a successful instantiation.
+    pub instantiated_text: String,
+    /// The button which opens the execute page of the instantiated contract.
+    pub execute_contract: Selector,
+    /// A regex for the URL of the execute page, which captures the address.
+    pub address_pattern: String,
+}
+
+/// The selectors of `ExecutePage`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExecuteSelectors {
+    /// The listbox for the message to send.
+    pub message_listbox: Selector,
+    /// A message in the listbox, `{}` is the name of the message.
+    pub message_option: Selector,
+    /// The listbox for RPC call vs. transaction, if RPC call is chosen.
+    pub rpc_listbox: Selector,
+    /// The RPC call option in the listbox.
+    pub rpc_option: Selector,
+    /// The listbox for RPC call vs. transaction, if transaction is chosen.
+    pub transaction_listbox: Selector,
+    /// The transaction option in the listbox.
+    pub transaction_option: Selector,
+    /// The listbox for the account which sends the message.
+    pub caller_listbox: Selector,
+    /// The checkbox which makes the UI estimate the gas.
+    pub estimated_gas_checkbox: Selector,
+    /// The input for the maximum gas allowed.
+    pub max_gas_input: Selector,
+    /// The listbox for the unit of the payment, `{}` is the selected unit.
+    pub payment_unit_listbox: Selector,
+    /// A unit in the listbox, `{}` is the unit.
+    pub payment_unit_option: Selector,
+    /// The input for the payment.
+    pub payment_input: Selector,
+    /// The input for an argument, `{}` is the label of the argument.
+    pub argument_input: Selector,
+    /// The button which sends the message.
+    pub call: Selector,
+    /// The output of the latest RPC call.
+    pub outcome: Selector,
+}
+
+/// The selectors of `AccountsPage`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AccountsSelectors {
+    /// The button which opens the modal to add an account.
+    pub add_account: Selector,
+    /// The listbox for the kind of secret, mnemonic phrase or seed.
+    pub seed_type_listbox: Selector,
+    /// A kind of secret in the listbox, `{}` is its label.
+    pub seed_type_option: Selector,
+    /// The input for the mnemonic phrase or the seed.
+    pub seed_input: Selector,
+    /// The checkbox which confirms that the secret was saved, if it exists.
+    pub seed_saved_checkbox: Selector,
+    /// The button which leads to the next step of the modal.
+    pub next: Selector,
+    /// The input for the name of the account.
+    pub name_input: Selector,
+    /// The input for the password of the account.
+    pub password_input: Selector,
+    /// The input for the repeated password of the account.
+    pub password_repeat_input: Selector,
+    /// The button which saves the account.
+    pub save: Selector,
+    /// The button which opens the modal to restore an account from a keystore.
+    pub restore_json: Selector,
+    /// The file input for the keystore, has to be a CSS selector.
+    pub keystore_input: Selector,
+    /// The input for the password of the keystore.
+    pub restore_password_input: Selector,
+    /// The button which restores the account.
+    pub restore: Selector,
+    /// The row of an account in the list, `{}` is the account name.
+    pub account_row: Selector,
+    /// The button which opens the transfer modal of an account, `{}` is the
+    /// account name.
+    pub send: Selector,
+    /// The input for the recipient of a transfer.
+    pub recipient_input: Selector,
+    /// The listbox for the unit of the amount, `{}` is the selected unit.
+    pub amount_unit_listbox: Selector,
+    /// A unit in the listbox, `{}` is the unit.
+    pub amount_unit_option: Selector,
+    /// The input for the amount of a transfer.
+    pub amount_input: Selector,
+    /// The button which submits the transfer.
+    pub make_transfer: Selector,
+}
+
+/// The selectors of `NotificationsPanel`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct NotificationsSelectors {
+    /// A notification, has to be a CSS selector.
+    pub notification: Selector,
+    /// The description of a notification.
+    pub description: Selector,
+    /// The header of a notification, relative to its description.
+    pub header: Selector,
+    /// The status of a notification, relative to its description.
+    pub status: Selector,
+    /// The notification of an extrinsic which was queued for signing.
+    pub queued: Selector,
+    /// The notification of an extrinsic which was either successful or failed.
+    pub extrinsic_result: Selector,
+    /// The button which dismisses all notifications.
+    pub dismiss_all: Selector,
+}
+
+/// Returns the release of the UI configured via the environment variable
+/// `CANVAS_UI_VERSION`.
+pub fn version_from_env() -> Option<String> {
+    std::env::var("CANVAS_UI_VERSION").ok()
+}
+
+/// Loads the profile for the release `version` of the UI.
+///
+/// If `version` is `None` or older than all profiles, the latest respectively
+/// the oldest profile is used.
+pub fn load(version: Option<&str>) -> Result<Selectors, String> {
+    let mut dirs = vec![profiles_dir()];
+    if let Ok(overrides) = std::env::var("WATERFALL_SELECTORS_OVERRIDES") {
+        dirs.push(PathBuf::from(overrides));
+    }
+    load_from(&dirs, version)
+}
+
+/// Loads the profile for the release `version` of the UI from the profiles in
+/// `dirs`, see `load`.
+///
+/// The profiles of later directories are applied after the ones of earlier
+/// directories with the same `since` version.
+fn load_from(dirs: &[PathBuf], version: Option<&str>) -> Result<Selectors, String> {
+    let mut profiles = Vec::new();
+    for dir in dirs {
+        profiles.extend(read_profiles(dir)?);
+    }
+    if profiles.is_empty() {
+        return Err(format!("there are no selectors in {:?}", dirs))
+    }
+    // the sort is stable, so overrides stay behind the profiles they override
+    profiles.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let index = match version {
+        Some(version) => {
+            let version = parse_version(version)
+                .ok_or_else(|| format!("unable to parse the UI version {:?}", version))?;
+            profiles
+                .iter()
+                .rposition(|(since, _)| since <= &version)
+                .unwrap_or(0)
+        }
+        None => profiles.len() - 1,
+    };
+    // the profiles which apply to the same release as the chosen one are applied
+    // as well, e.g. an override of it
+    let since = &profiles[index].0;
+    let end = profiles
+        .iter()
+        .rposition(|(other, _)| other == since)
+        .unwrap_or(index);
+
+    let mut merged = Value::Object(Default::default());
+    for (_, profile) in profiles.into_iter().take(end + 1) {
+        merge(&mut merged, profile);
+    }
+    let profile: Selectors = serde_json::from_value(merged).map_err(|err| {
+        format!("the selectors in {:?} are incomplete: {:?}", dirs, err)
+    })?;
+    log::info!(
+        "using the selectors since {} for the UI version {:?}",
+        profile.since,
+        version
+    );
+    Ok(profile)
+}
+
+/// Merges the selectors of `profile` into `merged`, replacing the ones which are
+/// in both.
+fn merge(merged: &mut Value, profile: Value) {
+    match (merged, profile) {
+        (Value::Object(merged), Value::Object(profile)) => {
+            for (key, value) in profile {
+                match merged.get_mut(&key) {
+                    Some(existing) if existing.is_object() && value.is_object() => {
+                        merge(existing, value)
+                    }
+                    _ => {
+                        merged.insert(key, value);
+                    }
+                }
+            }
+        }
+        (merged, profile) => *merged = profile,
+    }
+}
+
+/// Returns the directory which contains the profiles.
+///
+/// Defaults to the `selectors` directory of this crate, can be overridden with
+/// the environment variable `WATERFALL_SELECTORS_DIR`.
+fn profiles_dir() -> PathBuf {
+    std::env::var("WATERFALL_SELECTORS_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("selectors"))
+}
+
+/// Reads all profiles in `dir`, together with their parsed `since` version.
+fn read_profiles(dir: &Path) -> Result<Vec<(Vec<u64>, Value)>, String> {
+    let entries = fs::read_dir(dir)
+        .map_err(|err| format!("unable to read the selectors in {:?}: {:?}", dir, err))?;
+    let mut profiles = Vec::new();
+    for entry in entries {
+        let path = entry
+            .map_err(|err| {
+                format!("unable to read the selectors in {:?}: {:?}", dir, err)
+            })?
+            .path();
+        if path
+            .extension()
+            .map_or(true, |extension| extension != "json")
+        {
+            continue
+        }
+        let json = fs::read(&path)
+            .map_err(|err| format!("unable to read {:?}: {:?}", path, err))?;
+        let profile: Value = serde_json::from_slice(&json).map_err(|err| {
+            format!("the selectors in {:?} are malformed: {:?}", path, err)
+        })?;
+        let since = profile
+            .get("since")
+            .and_then(Value::as_str)
+            .and_then(parse_version)
+            .ok_or_else(|| format!("the `since` version in {:?} is invalid", path))?;
+        profiles.push((since, profile));
+    }
+    Ok(profiles)
+}
+
+/// Parses a version like `0.1.2` or `v0.1.2` into its numbers.
+fn parse_version(version: &str) -> Option<Vec<u64>> {
+    version
+        .trim()
+        .trim_start_matches('v')
+        .split('.')
+        .map(|number| number.parse().ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a directory with the complete profile `selectors/default.json` for
+    /// the releases since `0.2.0`, and a profile for the releases since `0.4.0`
+    /// which only changes the 'Skip Intro' button.
+    fn profiles(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "waterfall-selectors-{}-{}",
+            name,
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).expect("unable to create the profiles directory");
+        let default = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("selectors");
+        let mut oldest: Value = serde_json::from_slice(
+            &fs::read(default.join("default.json")).expect("unable to read the profile"),
+        )
+        .expect("the profile is malformed");
+        oldest["since"] = "0.2.0".into();
+        let newest = serde_json::json!({
+            "since": "0.4.0",
+            "upload": { "skip_intro": { "css": ".skip-intro" } },
+        });
+        for (file, profile) in [("oldest.json", oldest), ("newest.json", newest)] {
+            fs::write(dir.join(file), profile.to_string())
+                .expect("unable to write the profile");
+        }
+        dir
+    }
+
+    fn loaded(dirs: &[PathBuf], version: Option<&str>) -> (String, String) {
+        let selectors = load_from(dirs, version).expect("unable to load the selectors");
+        (
+            selectors.since,
+            selectors.upload.skip_intro.as_str().to_string(),
+        )
+    }
+
+    #[test]
+    fn load_picks_the_profile_for_the_version() {
+        let dirs = [profiles("versions")];
+        let skip_intro = "//button[contains(text(),'Skip Intro')]".to_string();
+
+        // below the oldest profile
+        assert_eq!(
+            loaded(&dirs, Some("0.1.9")),
+            ("0.2.0".to_string(), skip_intro.clone())
+        );
+        // between the profiles
+        assert_eq!(
+            loaded(&dirs, Some("0.3.1")),
+            ("0.2.0".to_string(), skip_intro.clone())
+        );
+        // at the bound of the newest profile
+        assert_eq!(
+            loaded(&dirs, Some("v0.4.0")),
+            ("0.4.0".to_string(), ".skip-intro".to_string())
+        );
+        // above the newest profile, the other selectors are kept
+        let selectors = load_from(&dirs, Some("1.0.0")).expect("unable to load");
+        assert_eq!(selectors.since, "0.4.0");
+        assert_eq!(selectors.upload.skip_intro.as_str(), ".skip-intro");
+        assert_eq!(selectors.upload.file_input.as_str(), ".ui--InputFile input");
+        // unknown version
+        assert_eq!(
+            loaded(&dirs, None),
+            ("0.4.0".to_string(), ".skip-intro".to_string())
+        );
+        fs::remove_dir_all(&dirs[0]).expect("unable to remove the profiles");
+    }
+
+    #[test]
+    fn load_applies_the_overrides() {
+        let shipped = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("selectors");
+        let overrides = shipped.join("overrides");
+
+        let selectors = load_from(&[shipped, overrides], Some("0.1.0"))
+            .expect("unable to load the selectors");
+
+        assert_eq!(selectors.since, "0.0.0");
+        assert_eq!(
+            selectors.upload.skip_intro.as_str(),
+            "//button[contains(text(),'Skip Introduction')]"
+        );
+        assert_eq!(selectors.upload.file_input.as_str(), ".ui--InputFile input");
+    }
+
+    #[test]
+    fn load_rejects_an_invalid_version() {
+        let shipped = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("selectors");
+        assert!(load_from(&[shipped], Some("latest")).is_err());
+    }
+}
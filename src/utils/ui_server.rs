@@ -0,0 +1,134 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A static HTTP server for a local `canvas-ui` build.
+//!
+//! The server runs in a background thread for the rest of the test run, all
+//! tests share it.
+
+use once_cell::sync::OnceCell;
+use std::{
+    fs,
+    path::{
+        Component,
+        Path,
+        PathBuf,
+    },
+};
+use tiny_http::{
+    Header,
+    Response,
+    Server,
+};
+
+/// The base URL of the server, once it has been started.
+static BASE_URL: OnceCell<String> = OnceCell::new();
+
+/// Returns the base URL under which the `canvas-ui` build in `build_dir` is served.
+///
+/// The server is started on a free port by the first invocation, later
+/// invocations return the URL of this server.
+pub fn serve(build_dir: &Path) -> Result<&'static str, Box<dyn std::error::Error>> {
+    BASE_URL
+        .get_or_try_init(|| start(build_dir.to_path_buf()))
+        .map(String::as_str)
+}
+
+/// Starts serving the files in `build_dir` on a free port.
+fn start(build_dir: PathBuf) -> Result<String, Box<dyn std::error::Error>> {
+    if !build_dir.join("index.html").is_file() {
+        return Err(format!(
+            "{:?} contains no `index.html`, is it a `canvas-ui` build (`yarn build`)?",
+            build_dir
+        )
+        .into())
+    }
+    let port = portpicker::pick_unused_port().expect("no free port");
+    let server = Server::http(("127.0.0.1", port))
+        .map_err(|err| format!("unable to start the ui server: {:?}", err))?;
+    let base_url = format!("http://127.0.0.1:{}", port);
+    log::info!(
+        "serving the canvas-ui build {:?} at {:?}",
+        build_dir,
+        base_url
+    );
+
+    std::thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let response = match resolve(&build_dir, request.url()) {
+                Some(file) => {
+                    match fs::read(&file) {
+                        Ok(content) => {
+                            Response::from_data(content)
+                                .with_header(content_type(&file))
+                                .boxed()
+                        }
+                        Err(err) => {
+                            log::warn!("unable to read {:?}: {:?}", file, err);
+                            Response::empty(500).boxed()
+                        }
+                    }
+                }
+                None => Response::empty(404).boxed(),
+            };
+            if let Err(err) = request.respond(response) {
+                log::warn!("unable to respond to a ui request: {:?}", err);
+            }
+        }
+    });
+    Ok(base_url)
+}
+
+/// Returns the file in `build_dir` which is requested by `url`.
+///
+/// Falls back to `index.html` for paths without a file, since the `canvas-ui`
+/// is a single page application. Paths leaving `build_dir` are rejected.
+fn resolve(build_dir: &Path, url: &str) -> Option<PathBuf> {
+    let path = url
+        .split(|c| c == '?' || c == '#')
+        .next()
+        .unwrap_or_default();
+    let path = Path::new(path.trim_start_matches('/'));
+    if path
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return None
+    }
+    let file = build_dir.join(path);
+    if file.is_file() {
+        Some(file)
+    } else {
+        Some(build_dir.join("index.html"))
+    }
+}
+
+/// Returns the `Content-Type` header for `file`.
+fn content_type(file: &Path) -> Header {
+    let content_type = match file.extension().and_then(|ext| ext.to_str()) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("js") => "application/javascript",
+        Some("css") => "text/css",
+        Some("json") | Some("map") => "application/json",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("ico") => "image/x-icon",
+        Some("wasm") => "application/wasm",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        Some("ttf") => "font/ttf",
+        _ => "application/octet-stream",
+    };
+    Header::from_bytes(&b"Content-Type"[..], content_type).expect("the header is valid")
+}
@@ -0,0 +1,410 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Checks `Call`s and `Upload`s against the contract metadata.
+//!
+//! This is done before any interaction with a backend, so that e.g. a typo in a
+//! message name fails immediately and not with a timeout in the UI minutes later.
+
+use super::{
+    backend::{
+        Call,
+        Upload,
+    },
+    contract_bundle::{
+        ArgSpec,
+        ContractBundle,
+    },
+};
+
+/// How a message is executed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CallKind {
+    /// As an RPC call, the result is not persisted on chain.
+    Rpc,
+    /// As a transaction.
+    Transaction,
+}
+
+/// Checks that the constructor and the initial values of `upload` match the
+/// contract `bundle`.
+pub fn check_upload(bundle: &ContractBundle, upload: &Upload) -> Result<(), String> {
+    let constructor = bundle
+        .constructor(upload.constructor.as_deref())
+        .ok_or_else(|| {
+            format!(
+                "the contract `{}` has no constructor {:?}, available constructors: {}",
+                bundle.contract.name,
+                upload.constructor.as_deref().unwrap_or_default(),
+                list(bundle.spec.constructors.iter().map(|c| c.label.as_str()))
+            )
+        })?;
+    check_args(
+        &format!("constructor `{}`", constructor.label),
+        &constructor.args,
+        &upload.initial_values,
+        upload.default_values,
+    )
+}
+
+/// Checks that the message and the values of `call` match the contract `bundle`,
+/// and that the message can be executed as `kind`.
+pub fn check_call(
+    bundle: &ContractBundle,
+    call: &Call,
+    kind: CallKind,
+) -> Result<(), String> {
+    let message = bundle.message(&call.method).ok_or_else(|| {
+        format!(
+            "the contract `{}` has no message {:?}, available messages: {}",
+            bundle.contract.name,
+            call.method,
+            list(bundle.spec.messages.iter().map(|m| m.label.as_str()))
+        )
+    })?;
+    match kind {
+        CallKind::Rpc if message.mutates => {
+            return Err(format!(
+                "the message `{}` mutates the contract's storage, it has to be \
+                executed as a transaction and not as an RPC call",
+                message.label
+            ))
+        }
+        CallKind::Transaction if !message.mutates && !message.payable => {
+            return Err(format!(
+                "the message `{}` neither mutates the contract's storage nor is it \
+                payable, it has to be executed as an RPC call and not as a transaction",
+                message.label
+            ))
+        }
+        _ => (),
+    }
+    if call.payment.is_some() && !message.payable {
+        return Err(format!(
+            "the message `{}` is not payable, but a payment was given",
+            message.label
+        ))
+    }
+    check_args(
+        &format!("message `{}`", message.label),
+        &message.args,
+        &call.values,
+        false,
+    )
+}
+
+/// Checks that each of the `values` belongs to exactly one of the `args`.
+///
+/// A type given in the key (e.g. `by: i32`) has to match the type of the
+/// argument. Arguments without a value are rejected, unless `default_values` is
+/// set, then they keep their default value.
+fn check_args(
+    what: &str,
+    args: &[ArgSpec],
+    values: &[(String, String)],
+    default_values: bool,
+) -> Result<(), String> {
+    if values.len() > args.len() {
+        return Err(format!(
+            "the {} takes {} argument(s), but {} were given",
+            what,
+            args.len(),
+            values.len()
+        ))
+    }
+    for (i, (key, _)) in values.iter().enumerate() {
+        let arg = args
+            .iter()
+            .find(|arg| normalize(&arg.label) == normalize(key))
+            .ok_or_else(|| {
+                format!(
+                    "the {} has no argument {:?}, available arguments: {}",
+                    what,
+                    key,
+                    list(args.iter().map(|arg| arg.label.as_str()))
+                )
+            })?;
+        if values[..i]
+            .iter()
+            .any(|(other, _)| normalize(other) == normalize(key))
+        {
+            return Err(format!(
+                "the argument {:?} of the {} was given more than once",
+                key, what
+            ))
+        }
+        if let (Some(expected), Some(actual)) =
+            (type_hint(key), arg.type_spec.display_name())
+        {
+            if expected != actual {
+                return Err(format!(
+                    "the argument {:?} of the {} is of type `{}`, not `{}`",
+                    arg.label, what, actual, expected
+                ))
+            }
+        }
+    }
+    if !default_values {
+        let missing = args
+            .iter()
+            .filter(|arg| {
+                !values
+                    .iter()
+                    .any(|(key, _)| normalize(key) == normalize(&arg.label))
+            })
+            .map(|arg| arg.label.as_str())
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            return Err(format!(
+                "the {} takes {} argument(s), but no value was given for {}",
+                what,
+                args.len(),
+                list(missing.into_iter())
+            ))
+        }
+    }
+    Ok(())
+}
+
+/// Normalizes an argument name or a UI label for comparison.
+///
+/// The UI labels the inputs in camel case and possibly with their type, i.e.
+/// `initialSupply` and `by: i32` are the labels of `initial_supply` and `by`.
+pub fn normalize(name: &str) -> String {
+    let name = name.split(':').next().unwrap_or_default();
+    name.trim().replace('_', "").to_lowercase()
+}
+
+/// Returns the type in a key like `by: i32`.
+fn type_hint(key: &str) -> Option<&str> {
+    key.split(':').nth(1).map(str::trim)
+}
+
+/// Returns the `names` as a comma separated list.
+fn list<'a>(names: impl Iterator<Item = &'a str>) -> String {
+    names
+        .map(|name| format!("`{}`", name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::balance::Balance;
+    use std::path::PathBuf;
+
+    /// A contract with the constructors `new(init_value: bool)` and `default()`
+    /// and the messages `inc(by: i32)`, `get()`, `transfer(to: AccountId, value:
+    /// Balance)` and `deposit()`, which is payable.
+    fn bundle() -> ContractBundle {
+        let arg = |label: &str, id: u64, name: &str| {
+            serde_json::json!({
+                "label": label,
+                "type": { "type": id, "displayName": [name] },
+            })
+        };
+        serde_json::from_value(serde_json::json!({
+            "source": { "hash": "0x00" },
+            "contract": { "name": "fixture" },
+            "spec": {
+                "constructors": [
+                    {
+                        "label": "new",
+                        "selector": "0x9bae9d5e",
+                        "args": [arg("init_value", 0, "bool")],
+                    },
+                    { "label": "default", "selector": "0xed4b9d1b" },
+                ],
+                "messages": [
+                    {
+                        "label": "inc",
+                        "selector": "0x1d32619f",
+                        "mutates": true,
+                        "args": [arg("by", 1, "i32")],
+                    },
+                    { "label": "get", "selector": "0x2f865bd9" },
+                    {
+                        "label": "transfer",
+                        "selector": "0x84a15da1",
+                        "mutates": true,
+                        "args": [arg("to", 2, "AccountId"), arg("value", 3, "Balance")],
+                    },
+                    {
+                        "label": "deposit",
+                        "selector": "0x2d10c9bd",
+                        "mutates": true,
+                        "payable": true,
+                    },
+                ],
+            },
+        }))
+        .expect("the fixture is malformed")
+    }
+
+    fn upload() -> Upload {
+        Upload::new(PathBuf::from("fixture.contract"))
+    }
+
+    fn call(method: &str) -> Call {
+        Call::new("5C4hrfjw9DjXZTzV3MwzrrAr9P1MJhSrvWGWqi1eSuyUpnhM", method)
+    }
+
+    #[test]
+    fn check_upload_accepts_matching_values() {
+        let bundle = bundle();
+        let new = upload().push_initial_value("initValue", "true");
+        let default = upload().constructor("default");
+
+        assert_eq!(check_upload(&bundle, &new), Ok(()));
+        assert_eq!(check_upload(&bundle, &default), Ok(()));
+    }
+
+    #[test]
+    fn check_upload_rejects_a_wrong_constructor() {
+        let upload = upload().constructor("create");
+
+        assert_eq!(
+            check_upload(&bundle(), &upload),
+            Err(
+                "the contract `fixture` has no constructor \"create\", available \
+                constructors: `new`, `default`"
+                    .into()
+            )
+        );
+    }
+
+    #[test]
+    fn check_upload_rejects_missing_values_without_default_values() {
+        let bundle = bundle();
+
+        assert_eq!(
+            check_upload(&bundle, &upload()),
+            Err(
+                "the constructor `new` takes 1 argument(s), but no value was given \
+                for `init_value`"
+                    .into()
+            )
+        );
+        assert_eq!(check_upload(&bundle, &upload().default_values()), Ok(()));
+    }
+
+    #[test]
+    fn check_call_rejects_a_wrong_message() {
+        assert_eq!(
+            check_call(&bundle(), &call("increment"), CallKind::Transaction),
+            Err(
+                "the contract `fixture` has no message \"increment\", available \
+                messages: `inc`, `get`, `transfer`, `deposit`"
+                    .into()
+            )
+        );
+    }
+
+    #[test]
+    fn check_call_rejects_a_wrong_argument() {
+        let call = call("inc").push_value("amount", "1");
+
+        assert_eq!(
+            check_call(&bundle(), &call, CallKind::Transaction),
+            Err(
+                "the message `inc` has no argument \"amount\", available arguments: \
+                `by`"
+                    .into()
+            )
+        );
+    }
+
+    #[test]
+    fn check_call_rejects_a_duplicate_argument() {
+        let call = call("transfer")
+            .push_value("to", "alice")
+            .push_value("to", "bob");
+
+        assert_eq!(
+            check_call(&bundle(), &call, CallKind::Transaction),
+            Err(
+                "the argument \"to\" of the message `transfer` was given more than \
+                once"
+                    .into()
+            )
+        );
+    }
+
+    #[test]
+    fn check_call_rejects_a_wrong_arity() {
+        let bundle = bundle();
+        let too_many = call("inc").push_value("by", "1").push_value("by", "2");
+        let too_few = call("transfer").push_value("to", "alice");
+
+        assert_eq!(
+            check_call(&bundle, &too_many, CallKind::Transaction),
+            Err("the message `inc` takes 1 argument(s), but 2 were given".into())
+        );
+        assert_eq!(
+            check_call(&bundle, &too_few, CallKind::Transaction),
+            Err(
+                "the message `transfer` takes 2 argument(s), but no value was given \
+                for `value`"
+                    .into()
+            )
+        );
+    }
+
+    #[test]
+    fn check_call_checks_the_type_hint() {
+        let bundle = bundle();
+        let matching = call("inc").push_value("by: i32", "-1");
+        let wrong = call("inc").push_value("by: u32", "1");
+
+        assert_eq!(
+            check_call(&bundle, &matching, CallKind::Transaction),
+            Ok(())
+        );
+        assert_eq!(
+            check_call(&bundle, &wrong, CallKind::Transaction),
+            Err(
+                "the argument \"by\" of the message `inc` is of type `i32`, not `u32`"
+                    .into()
+            )
+        );
+    }
+
+    #[test]
+    fn check_call_checks_the_kind_and_the_payment() {
+        let bundle = bundle();
+        let inc = call("inc").push_value("by", "1");
+
+        assert_eq!(check_call(&bundle, &call("get"), CallKind::Rpc), Ok(()));
+        assert!(check_call(&bundle, &call("get"), CallKind::Transaction).is_err());
+        assert!(check_call(&bundle, &inc, CallKind::Rpc).is_err());
+
+        let deposit = call("deposit").payment(Balance::units(1));
+        assert_eq!(check_call(&bundle, &deposit, CallKind::Transaction), Ok(()));
+        assert_eq!(
+            check_call(
+                &bundle,
+                &inc.payment(Balance::units(1)),
+                CallKind::Transaction
+            ),
+            Err("the message `inc` is not payable, but a payment was given".into())
+        );
+    }
+
+    #[test]
+    fn normalize_matches_ui_labels() {
+        assert_eq!(normalize("initialSupply"), normalize("initial_supply"));
+        assert_eq!(normalize("by: i32"), "by");
+    }
+}
@@ -0,0 +1,127 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The values returned by contracts, decoded with their types from the metadata.
+
+use std::fmt;
+
+/// A value decoded from its SCALE encoding.
+///
+/// Structs with a single unnamed field are represented by the value of this
+/// field, so that e.g. an `AccountId` is its byte array.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Char(char),
+    Str(String),
+    /// An unsigned integer, also a compact encoded one.
+    UInt(u128),
+    Int(i128),
+    /// A `[u8; N]` or a `Vec<u8>`.
+    Bytes(Vec<u8>),
+    /// Any other array or sequence.
+    Seq(Vec<Value>),
+    Tuple(Vec<Value>),
+    /// A struct with its fields, the names are `None` for tuple structs.
+    Composite(Vec<(Option<String>, Value)>),
+    /// A variant of an enum with its fields, e.g. `Some`, `Ok` or `Err`.
+    Variant(String, Vec<(Option<String>, Value)>),
+}
+
+impl Value {
+    /// Returns the value if this is a `bool`.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns the value if this is an unsigned integer.
+    pub fn as_u128(&self) -> Option<u128> {
+        match self {
+            Value::UInt(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns the value if this is a signed integer.
+    pub fn as_i128(&self) -> Option<i128> {
+        match self {
+            Value::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns the bytes if this is a byte array or a `Vec<u8>`.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::Bytes(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// Formats the value like the `canvas-ui` does, byte arrays are formatted as
+/// `0x`-prefixed hex, but not shortened.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Char(value) => write!(f, "{}", value),
+            Value::Str(value) => write!(f, "{}", value),
+            Value::UInt(value) => write!(f, "{}", value),
+            Value::Int(value) => write!(f, "{}", value),
+            Value::Bytes(value) => write!(f, "0x{}", hex::encode(value)),
+            Value::Seq(elems) => write!(f, "[{}]", join(elems)),
+            Value::Tuple(elems) => write!(f, "({})", join(elems)),
+            Value::Composite(fields) => write!(f, "{}", Fields(fields)),
+            Value::Variant(name, fields) if fields.is_empty() => write!(f, "{}", name),
+            Value::Variant(name, fields) => write!(f, "{}({})", name, Fields(fields)),
+        }
+    }
+}
+
+/// Formats the fields of a struct or variant.
+///
+/// A single unnamed field is formatted as its plain value.
+struct Fields<'a>(&'a [(Option<String>, Value)]);
+
+impl fmt::Display for Fields<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let [(None, value)] = self.0 {
+            return write!(f, "{}", value)
+        }
+        let fields = self
+            .0
+            .iter()
+            .map(|(name, value)| {
+                match name {
+                    Some(name) => format!("{}: {}", name, value),
+                    None => value.to_string(),
+                }
+            })
+            .collect::<Vec<_>>();
+        write!(f, "{{ {} }}", fields.join(", "))
+    }
+}
+
+/// Returns the `elems` separated by commas.
+fn join(elems: &[Value]) -> String {
+    elems
+        .iter()
+        .map(Value::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,257 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Condition-based waits for the browser.
+//!
+//! Instead of sleeping for a fixed amount of time, a condition is polled until
+//! it holds or the timeout is reached. The polling doesn't block the runtime.
+
+use fantoccini::{
+    error::CmdError,
+    Client,
+    Element,
+    Locator,
+};
+use serde::Deserialize;
+use std::{
+    fmt,
+    future::Future,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+/// How long we wait for a condition by default.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often a condition is checked by default.
+const DEFAULT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Returns the state of the first element matching a selector, or `null` if
+/// there is none.
+///
+/// The arguments are the kind of the selector (`css` or `xpath`) and the selector.
+const ELEMENT_STATE_SCRIPT: &str = "\
+    var el = arguments[0] === 'css'\
+        ? document.querySelector(arguments[1])\
+        : document.evaluate(arguments[1], document, null,\
+            XPathResult.FIRST_ORDERED_NODE_TYPE, null).singleNodeValue;\
+    if (!el) { return null; }\
+    var rect = el.getBoundingClientRect();\
+    var style = window.getComputedStyle(el);\
+    return {\
+        visible: rect.width > 0 && rect.height > 0\
+            && style.visibility !== 'hidden' && style.display !== 'none',\
+        enabled: !el.disabled && el.getAttribute('aria-disabled') !== 'true',\
+        rect: [rect.x, rect.y, rect.width, rect.height]\
+    };";
+
+/// An error while waiting for a condition.
+#[derive(Debug)]
+pub enum WaitError {
+    /// The condition didn't hold within the timeout.
+    Timeout {
+        /// What was waited for.
+        what: String,
+        /// How long was waited.
+        timeout: Duration,
+    },
+    /// Checking the condition failed.
+    Cmd(CmdError),
+}
+
+impl fmt::Display for WaitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WaitError::Timeout { what, timeout } => {
+                write!(f, "waited {:?} for {}, but it didn't happen", timeout, what)
+            }
+            WaitError::Cmd(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for WaitError {}
+
+impl From<CmdError> for WaitError {
+    fn from(err: CmdError) -> Self {
+        WaitError::Cmd(err)
+    }
+}
+
+/// The state of an element, as returned by `ELEMENT_STATE_SCRIPT`.
+#[derive(Debug, PartialEq, Deserialize)]
+struct ElementState {
+    visible: bool,
+    enabled: bool,
+    rect: [f64; 4],
+}
+
+/// Waits until a condition holds.
+///
+/// # Usage
+///
+/// ```no_compile
+/// let button = Wait::new("the upload button")
+///     .timeout(Duration::from_secs(5))
+///     .until_clickable(&client, Locator::Css(".upload"))
+///     .await?;
+/// ```
+pub struct Wait {
+    what: String,
+    timeout: Duration,
+    interval: Duration,
+}
+
+impl Wait {
+    /// Creates a wait for `what`, which is used in the log and in errors.
+    pub fn new(what: &str) -> Self {
+        Self {
+            what: what.to_string(),
+            timeout: DEFAULT_TIMEOUT,
+            interval: DEFAULT_INTERVAL,
+        }
+    }
+
+    /// Sets how long we wait at most.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets how often the condition is checked.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Polls `condition` until it returns `Some` and returns its value.
+    pub async fn until<T, F, Fut>(&self, mut condition: F) -> Result<T, WaitError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<Option<T>, CmdError>>,
+    {
+        let started = Instant::now();
+        loop {
+            if let Some(value) = condition().await? {
+                log::info!("waited {:?} for {}", started.elapsed(), self.what);
+                return Ok(value)
+            }
+            self.check_timeout(started)?;
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+
+    /// Waits until an element matching `locator` is visible.
+    pub async fn until_visible(
+        &self,
+        client: &Client,
+        locator: Locator<'_>,
+    ) -> Result<Element, WaitError> {
+        self.until_state(client, locator, |state, _| state.visible)
+            .await
+    }
+
+    /// Waits until an element matching `locator` is visible and enabled.
+    pub async fn until_clickable(
+        &self,
+        client: &Client,
+        locator: Locator<'_>,
+    ) -> Result<Element, WaitError> {
+        self.until_state(client, locator, |state, _| state.visible && state.enabled)
+            .await
+    }
+
+    /// Waits until an element matching `locator` is visible and didn't move since
+    /// the last check, i.e. its animation finished.
+    pub async fn until_animation_finished(
+        &self,
+        client: &Client,
+        locator: Locator<'_>,
+    ) -> Result<Element, WaitError> {
+        self.until_state(client, locator, |state, previous| {
+            state.visible && previous.map(|previous| previous.rect) == Some(state.rect)
+        })
+        .await
+    }
+
+    /// Waits until an element containing `text` is visible.
+    pub async fn until_text_present(
+        &self,
+        client: &Client,
+        text: &str,
+    ) -> Result<Element, WaitError> {
+        let path = format!("//*[contains(text(),'{}')]", text);
+        self.until_visible(client, Locator::XPath(&path)).await
+    }
+
+    /// Waits until the state of the element matching `locator` fulfills
+    /// `condition`, which also gets the state of the previous check.
+    async fn until_state<C>(
+        &self,
+        client: &Client,
+        locator: Locator<'_>,
+        condition: C,
+    ) -> Result<Element, WaitError>
+    where
+        C: Fn(&ElementState, Option<&ElementState>) -> bool,
+    {
+        let mut client = client.clone();
+        let (kind, selector) = selector(locator);
+        let started = Instant::now();
+        let mut previous = None;
+        loop {
+            let state = client
+                .execute(
+                    ELEMENT_STATE_SCRIPT,
+                    vec![kind.into(), selector.clone().into()],
+                )
+                .await?;
+            let state: Option<ElementState> =
+                serde_json::from_value(state).map_err(CmdError::Json)?;
+            if let Some(state) = &state {
+                if condition(state, previous.as_ref()) {
+                    log::info!("waited {:?} for {}", started.elapsed(), self.what);
+                    return Ok(client.find(locator).await?)
+                }
+            }
+            previous = state;
+            self.check_timeout(started)?;
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+
+    /// Returns an error if we waited since `started` for longer than the timeout.
+    fn check_timeout(&self, started: Instant) -> Result<(), WaitError> {
+        if started.elapsed() > self.timeout {
+            log::info!("waited {:?} for {} in vain", started.elapsed(), self.what);
+            return Err(WaitError::Timeout {
+                what: self.what.clone(),
+                timeout: self.timeout,
+            })
+        }
+        Ok(())
+    }
+}
+
+/// Returns the kind (`css` or `xpath`) and the selector for `locator`.
+fn selector(locator: Locator<'_>) -> (&'static str, String) {
+    match locator {
+        Locator::Css(css) => ("css", css.to_string()),
+        Locator::Id(id) => ("css", format!("#{}", id)),
+        Locator::LinkText(text) => ("xpath", format!("//a[text() = '{}']", text)),
+        Locator::XPath(path) => ("xpath", path.to_string()),
+    }
+}